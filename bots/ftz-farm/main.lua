@@ -2,14 +2,7 @@
 -- Simple auto-clicker bot for "向僵尸开炮" (Fire the Zombies)
 
 local win = nil
-
--- Click positions as [x_ratio, y_ratio, cooldown_seconds]
-local positions = {
-    { 0.85, 0.75, 3 },   -- right1
-    { 0.50, 0.80, 5 },   -- start
-    { 0.50, 0.50, 3 },   -- done
-    { 0.30, 0.70, 3 },   -- tower
-}
+local positions = {}
 
 local timers = {}
 local current = 1
@@ -18,9 +11,24 @@ return {
     window_pattern = "僵尸|zombie",
     description = "Auto-clicker for Fire the Zombies",
 
-    start = function(w)
+    options = {
+        {
+            name = "positions",
+            type = "table",
+            description = "Click points as {x_ratio, y_ratio, cooldown_seconds}",
+            default = {
+                { 0.85, 0.75, 3 },   -- right1
+                { 0.50, 0.80, 5 },   -- start
+                { 0.50, 0.50, 3 },   -- done
+                { 0.30, 0.70, 3 },   -- tower
+            },
+        },
+    },
+
+    start = function(w, opts)
         win = w
-        local now = os.clock()
+        positions = opts.positions
+        local now = F.now()
         for i = 1, #positions do
             timers[i] = now
         end
@@ -28,14 +36,14 @@ return {
     end,
 
     tick = function()
-        local now = os.clock()
+        local now = F.now()
         local pos = positions[current]
         if now >= timers[current] then
             win:click(pos[1], pos[2])
             timers[current] = now + pos[3]
         end
         current = current % #positions + 1
-        return 700
+        return 0.7
     end,
 
     get_status = function()
@@ -43,7 +51,7 @@ return {
     end,
 
     reset = function()
-        local now = os.clock()
+        local now = F.now()
         for i = 1, #positions do
             timers[i] = now
         end
@@ -8,7 +8,7 @@ local win = nil
 local chars = {} -- {[id] = state}
 local pos = 1 -- which char slot we're on (0 = watcher)
 local last_login = 0 -- anti-AFK timer (0 = trigger on first tick)
-local SW, BB = 1453, 1434 -- zone IDs for Stormwind and Booty Bay
+local SW, BB -- zone IDs for Stormwind and Booty Bay (from options)
 
 local function reset()
     chars = {
@@ -182,9 +182,15 @@ return {
     window_pattern = "World of Warcraft|wow|魔兽世界",
     description = "Rally+HK buff coordination",
 
+    options = {
+        { name = "stormwind_zone", default = 1453, description = "Zone ID of Stormwind" },
+        { name = "booty_bay_zone", default = 1434, description = "Zone ID of Booty Bay" },
+    },
+
     reset = reset,
-    start = function(w)
+    start = function(w, opts)
         win = w
+        SW, BB = opts.stormwind_zone, opts.booty_bay_zone
     end,
     stop = function()
     end,
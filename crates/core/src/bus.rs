@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde_json::{Map, Value};
+
+/// Scope name used by `F.global`; bot-scoped traffic uses the bot name.
+pub const GLOBAL: &str = "*";
+
+/// A value published by one bot instance for the others.
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// Bot name, or `GLOBAL`
+    pub scope: String,
+    pub topic: String,
+    pub value: Value,
+    /// Instance id of the publisher
+    pub from: String,
+}
+
+/// Blackboard behind `F.shared` / `F.global.shared`.
+pub type Blackboard = Arc<Mutex<Map<String, Value>>>;
+
+static OUTBOX: Mutex<Vec<Message>> = Mutex::new(Vec::new());
+static SHARED: OnceLock<Mutex<HashMap<String, Blackboard>>> = OnceLock::new();
+
+/// Queue a message. The orchestrator routes it after the current tick.
+pub fn publish(msg: Message) {
+    OUTBOX.lock().unwrap().push(msg);
+}
+
+/// Take every message published since the last call.
+pub fn drain() -> Vec<Message> {
+    std::mem::take(&mut *OUTBOX.lock().unwrap())
+}
+
+/// The shared blackboard for `scope`, created on first use.
+pub fn shared(scope: &str) -> Blackboard {
+    let tables = SHARED.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut tables = tables.lock().unwrap();
+    Arc::clone(tables.entry(scope.to_string()).or_default())
+}
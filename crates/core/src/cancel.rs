@@ -0,0 +1,35 @@
+use std::cell::OnceCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// Stop flag of the bot instance whose worker owns this thread
+    static LOCAL: OnceCell<Arc<AtomicBool>> = const { OnceCell::new() };
+}
+
+/// Ask everything in flight to stop: interruptible sleeps return early, window
+/// input is refused and the running tick is aborted. Set by the panic hotkey,
+/// by stopping the orchestrator and on quit.
+pub fn request() {
+    REQUESTED.store(true, Ordering::Release);
+}
+
+/// Whether a stop has been requested since the last `clear`, for everything
+/// or for the instance running on this thread.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Acquire)
+        || LOCAL.with(|l| l.get().is_some_and(|f| f.load(Ordering::Acquire)))
+}
+
+/// Re-arm after the stop completed, before bots run again.
+pub fn clear() {
+    REQUESTED.store(false, Ordering::Release);
+}
+
+/// Also honour `flag` on the calling thread, so one instance can be stopped
+/// without touching the others. Call once when its thread starts.
+pub fn bind(flag: Arc<AtomicBool>) {
+    LOCAL.with(|l| { l.set(flag).ok(); });
+}
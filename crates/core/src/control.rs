@@ -0,0 +1,558 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+use serde_json::{Value, json};
+
+use crate::cancel;
+use crate::logger;
+use crate::orchestrator;
+use crate::settings::Settings;
+use crate::types::*;
+
+#[cfg(unix)]
+type Listener = std::os::unix::net::UnixListener;
+#[cfg(unix)]
+type Stream = std::os::unix::net::UnixStream;
+#[cfg(windows)]
+type Listener = std::net::TcpListener;
+#[cfg(windows)]
+type Stream = std::net::TcpStream;
+
+/// How often a `subscribe`d connection is checked for status changes
+const STATUS_POLL: Duration = Duration::from_millis(500);
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Request without the endpoint's token
+const UNAUTHORIZED: i64 = -32001;
+
+/// Control endpoint in `dir`: a Unix socket, or on Windows a file holding
+/// the port of a loopback TCP socket and the token every request must carry.
+pub fn endpoint(dir: &Path) -> PathBuf {
+    dir.join(if cfg!(windows) { "finger.port" } else { "finger.sock" })
+}
+
+/// Bind the socket in a private directory and move it into place once only
+/// the owner may connect, so it is never open to other users.
+#[cfg(unix)]
+fn bind(path: &Path) -> io::Result<(Listener, Option<String>)> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    if path.exists() {
+        if Stream::connect(path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, "another finger is already running here"));
+        }
+        // Left behind by a finger that did not shut down cleanly
+        std::fs::remove_file(path)?;
+    }
+    let mut private = path.as_os_str().to_owned();
+    private.push(format!(".{}", std::process::id()));
+    let private = PathBuf::from(private);
+    std::fs::DirBuilder::new().mode(0o700).create(&private)?;
+    let temp = private.join("s");
+    let listener = Listener::bind(&temp).and_then(|listener| {
+        std::fs::set_permissions(&temp, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&temp, path)?;
+        Ok(listener)
+    });
+    std::fs::remove_dir_all(&private).ok();
+    Ok((listener?, None))
+}
+
+/// Any local process can reach a loopback port, so requests must present
+/// the random token written next to the port.
+#[cfg(windows)]
+fn bind(path: &Path) -> io::Result<(Listener, Option<String>)> {
+    use rand::Rng;
+
+    if connect(path).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AddrInUse, "another finger is already running here"));
+    }
+    let listener = Listener::bind("127.0.0.1:0")?;
+    let token = format!("{:032x}", rand::thread_rng().gen::<u128>());
+    std::fs::write(path, format!("{}\n{}\n", listener.local_addr()?.port(), token))?;
+    Ok((listener, Some(token)))
+}
+
+#[cfg(unix)]
+fn connect(path: &Path) -> io::Result<(Stream, Option<String>)> {
+    Ok((Stream::connect(path)?, None))
+}
+
+#[cfg(windows)]
+fn connect(path: &Path) -> io::Result<(Stream, Option<String>)> {
+    let bad = || io::Error::new(io::ErrorKind::InvalidData, "bad port file");
+    let contents = std::fs::read_to_string(path)?;
+    let mut lines = contents.lines();
+    let port: u16 = lines.next().and_then(|p| p.trim().parse().ok()).ok_or_else(bad)?;
+    let token = lines.next().map(|t| t.trim().to_string()).ok_or_else(bad)?;
+    Ok((Stream::connect(("127.0.0.1", port))?, Some(token)))
+}
+
+/// Remove the endpoint file on shutdown.
+pub fn cleanup(path: &Path) {
+    std::fs::remove_file(path).ok();
+}
+
+/// A failed call, sent back as a JSON-RPC error object
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+/// One parsed request line
+#[derive(Debug, PartialEq)]
+struct Request {
+    /// None for a notification, which gets no reply
+    id: Option<Value>,
+    method: String,
+    params: Value,
+}
+
+/// Parse one line as a JSON-RPC 2.0 request carrying `token`, if the
+/// endpoint has one. An error comes with the request id when it was readable.
+fn parse_request(line: &str, token: Option<&str>) -> Result<Request, (Value, RpcError)> {
+    let req: Value = serde_json::from_str(line)
+        .map_err(|e| (Value::Null, RpcError::new(PARSE_ERROR, e.to_string())))?;
+    let id = req.get("id").cloned();
+    let fail = |code, message: &str| (id.clone().unwrap_or(Value::Null), RpcError::new(code, message));
+    if !req.is_object() || req.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err(fail(INVALID_REQUEST, "not a JSON-RPC 2.0 request"));
+    }
+    if token.is_some() && req.get("token").and_then(Value::as_str) != token {
+        return Err(fail(UNAUTHORIZED, "missing or wrong token"));
+    }
+    let method = req.get("method").and_then(Value::as_str)
+        .ok_or_else(|| fail(INVALID_REQUEST, "missing method"))?;
+    Ok(Request {
+        method: method.to_string(),
+        params: req.get("params").cloned().unwrap_or(Value::Null),
+        id,
+    })
+}
+
+fn error_reply(id: Value, e: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0", "id": id,
+        "error": { "code": e.code, "message": e.message },
+    })
+}
+
+/// What the control connections share with the orchestrator
+struct Shared {
+    state: Arc<Mutex<Vec<BotEntry>>>,
+    orch_state: Arc<Mutex<OrchestratorState>>,
+    cmd_tx: Mutex<mpsc::Sender<Command>>,
+    /// Where switching bots on and off is saved; None keeps it in memory
+    settings_path: Option<PathBuf>,
+    /// Log lines for every connection that subscribed to them
+    log_subs: Mutex<Vec<mpsc::Sender<String>>>,
+    /// What every request must present, on endpoints other users could reach
+    token: Option<String>,
+}
+
+/// Serve the control API on `path` from background threads. Takes over the
+/// logger's channel: each entry is printed to stdout as a plain line and
+/// sent to subscribed connections. Enable and disable calls are saved to
+/// `settings_path` if given.
+pub fn serve(
+    path: &Path,
+    state: Arc<Mutex<Vec<BotEntry>>>,
+    orch_state: Arc<Mutex<OrchestratorState>>,
+    cmd_tx: mpsc::Sender<Command>,
+    settings_path: Option<PathBuf>,
+    log_rx: mpsc::Receiver<String>,
+) -> io::Result<()> {
+    let (listener, token) = bind(path)?;
+    let shared = Arc::new(Shared {
+        state,
+        orch_state,
+        cmd_tx: Mutex::new(cmd_tx),
+        settings_path,
+        log_subs: Mutex::new(Vec::new()),
+        token,
+    });
+
+    let logs = Arc::clone(&shared);
+    thread::spawn(move || {
+        for entry in log_rx {
+            let line = logger::plain_line(&entry);
+            println!("{}", line);
+            logs.log_subs.lock().unwrap().retain(|tx| tx.send(line.clone()).is_ok());
+        }
+    });
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                handle_connection(&shared, stream).ok();
+            });
+        }
+    });
+    Ok(())
+}
+
+fn send(writer: &Mutex<Stream>, msg: &Value) -> io::Result<()> {
+    // One write per message, so a line is never split between writers
+    writer.lock().unwrap().write_all(format!("{}\n", msg).as_bytes())
+}
+
+/// Answer newline-delimited JSON-RPC requests until the client hangs up.
+/// The first line that is not a valid request is answered with an error
+/// and ends the connection: whatever sent it is not a finger client.
+fn handle_connection(shared: &Arc<Shared>, stream: Stream) -> io::Result<()> {
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let req = match parse_request(&line, shared.token.as_deref()) {
+            Ok(req) => req,
+            Err((id, e)) => return send(&writer, &error_reply(id, e)),
+        };
+        let result = shared.call(&req.method, &req.params, &writer);
+        // Requests without an id are notifications and get no reply
+        let Some(id) = req.id else { continue };
+        let reply = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_reply(id, e),
+        };
+        send(&writer, &reply)?;
+    }
+    Ok(())
+}
+
+fn str_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    params.get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("missing string param \"{}\"", name)))
+}
+
+impl Shared {
+    fn call(self: &Arc<Self>, method: &str, params: &Value, writer: &Arc<Mutex<Stream>>) -> Result<Value, RpcError> {
+        match method {
+            "list" => Ok(self.list()),
+            "start" | "stop" => {
+                let current = *self.orch_state.lock().unwrap();
+                let wanted = if method == "start" { OrchestratorState::Stopped } else { OrchestratorState::Running };
+                if current == wanted {
+                    orchestrator::start_stop(&self.orch_state, &self.cmd_tx.lock().unwrap());
+                }
+                Ok(json!({ "state": state_name(*self.orch_state.lock().unwrap()) }))
+            }
+            "restart" => {
+                self.send(Command::Restart(0));
+                Ok(Value::Null)
+            }
+            "enable" | "disable" => self.set_enabled(params, method == "enable"),
+            "action" => {
+                let (idx, entry_instances) = self.find_bot(str_param(params, "bot")?)?;
+                let action = str_param(params, "action")?.to_string();
+                // Accept an instance id or its key
+                let instance = match params.get("instance").and_then(Value::as_str) {
+                    Some(name) => Some(entry_instances.into_iter()
+                        .find(|(id, key)| id == name || key == name)
+                        .map(|(id, _)| id)
+                        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("no instance \"{}\"", name)))?),
+                    None => None,
+                };
+                self.send(Command::Action { bot: idx, instance, action });
+                Ok(Value::Null)
+            }
+            "abandon" => {
+                let (idx, entry_instances) = self.find_bot(str_param(params, "bot")?)?;
+                let name = str_param(params, "instance")?;
+                let key = entry_instances.into_iter()
+                    .find(|(id, key)| id == name || key == name)
+                    .map(|(_, key)| key)
+                    .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("no instance \"{}\"", name)))?;
+                self.send(Command::Abandon { bot: idx, key });
+                Ok(Value::Null)
+            }
+            "subscribe" => {
+                let logs = params.get("logs").and_then(Value::as_bool).unwrap_or(true);
+                let status = params.get("status").and_then(Value::as_bool).unwrap_or(true);
+                self.subscribe(logs, status, Arc::clone(writer));
+                Ok(json!({ "logs": logs, "status": status }))
+            }
+            "quit" => {
+                cancel::request();
+                self.send(Command::Quit);
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method \"{}\"", method))),
+        }
+    }
+
+    fn send(&self, cmd: Command) {
+        self.cmd_tx.lock().unwrap().send(cmd).ok();
+    }
+
+    /// Index of the bot called `name` and its instances as (id, key).
+    fn find_bot(&self, name: &str) -> Result<(usize, Vec<(String, String)>), RpcError> {
+        let entries = self.state.lock().unwrap();
+        entries.iter()
+            .position(|e| e.name == name)
+            .map(|i| (i, entries[i].instances.iter().map(|x| (x.id.clone(), x.key.clone())).collect()))
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("no bot \"{}\"", name)))
+    }
+
+    /// Switch a bot, or one of its instances (by key or id), on or off and
+    /// save the choice like the TUI does.
+    fn set_enabled(&self, params: &Value, enabled: bool) -> Result<Value, RpcError> {
+        let name = str_param(params, "bot")?;
+        let cmd = {
+            let mut entries = self.state.lock().unwrap();
+            let idx = entries.iter().position(|e| e.name == name)
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("no bot \"{}\"", name)))?;
+            let entry = &mut entries[idx];
+            let cmd = match params.get("instance").and_then(Value::as_str) {
+                Some(inst) => {
+                    let inst = entry.instances.iter_mut()
+                        .find(|i| i.key == inst || i.id == inst)
+                        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("no instance \"{}\"", inst)))?;
+                    if inst.enabled == enabled {
+                        return Ok(json!({ "changed": false }));
+                    }
+                    inst.enabled = enabled;
+                    Command::ToggleInstance { bot: idx, key: inst.key.clone() }
+                }
+                None => {
+                    if entry.enabled == enabled {
+                        return Ok(json!({ "changed": false }));
+                    }
+                    entry.enabled = enabled;
+                    Command::Toggle(idx)
+                }
+            };
+            if let Some(path) = &self.settings_path {
+                let mut settings = Settings::load(path);
+                settings.update(&entries);
+                settings.save(path);
+            }
+            cmd
+        };
+        self.send(cmd);
+        Ok(json!({ "changed": true }))
+    }
+
+    /// Run state and every bot with its instances, as `list` returns them.
+    fn list(&self) -> Value {
+        let entries = self.state.lock().unwrap();
+        let bots: Vec<Value> = entries.iter().map(|e| json!({
+            "name": e.name,
+            "description": e.description,
+            "window_pattern": e.window_pattern,
+            "enabled": e.enabled,
+            "hold": e.hold.as_ref().map(|h| h.describe()),
+            "instances": e.instances.iter().map(|i| json!({
+                "id": i.id,
+                "key": i.key,
+                "window_id": i.window_id,
+                "title": i.window_title,
+                "enabled": i.enabled,
+                "status": i.status.text,
+                "progress": i.status.progress,
+                "fields": i.status.fields.iter()
+                    .map(|(k, v)| (k.clone(), Value::from(v.clone())))
+                    .collect::<serde_json::Map<_, _>>(),
+                "error": i.error,
+                "stuck": i.stuck.map(|(since, step)| json!({
+                    "step": step,
+                    "secs": since.elapsed().as_secs(),
+                })),
+            })).collect::<Vec<_>>(),
+        })).collect();
+        json!({ "state": state_name(*self.orch_state.lock().unwrap()), "bots": bots })
+    }
+
+    /// Push `log` and `status` notifications to the connection until it
+    /// hangs up. Status goes out whenever `list` would answer differently.
+    fn subscribe(self: &Arc<Self>, logs: bool, status: bool, writer: Arc<Mutex<Stream>>) {
+        let (tx, rx) = mpsc::channel::<String>();
+        if logs {
+            self.log_subs.lock().unwrap().push(tx.clone());
+        }
+        let shared = Arc::clone(self);
+        thread::spawn(move || {
+            // Keeps the channel open when only status was asked for
+            let _tx = tx;
+            let mut last = Value::Null;
+            loop {
+                let sent = match rx.recv_timeout(STATUS_POLL) {
+                    Ok(line) => send(&writer, &json!({
+                        "jsonrpc": "2.0", "method": "log", "params": { "line": line },
+                    })),
+                    Err(_) if status => {
+                        let now = shared.list();
+                        if now == last {
+                            continue;
+                        }
+                        last = now.clone();
+                        send(&writer, &json!({ "jsonrpc": "2.0", "method": "status", "params": now }))
+                    }
+                    Err(_) => Ok(()),
+                };
+                if sent.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+fn state_name(state: OrchestratorState) -> &'static str {
+    match state {
+        OrchestratorState::Running => "running",
+        OrchestratorState::Stopping => "stopping",
+        OrchestratorState::Stopped => "stopped",
+    }
+}
+
+/// Connection to a running finger's control endpoint
+pub struct Client {
+    reader: BufReader<Stream>,
+    writer: Stream,
+    next_id: u64,
+    token: Option<String>,
+}
+
+impl Client {
+    pub fn connect(path: &Path) -> Result<Self> {
+        let (stream, token) = connect(path)
+            .map_err(|e| anyhow!("cannot reach finger at {}: {}", path.display(), e))?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            next_id: 1,
+            token,
+        })
+    }
+
+    /// Call `method` and wait for its result. Notifications arriving in
+    /// between are dropped.
+    pub fn call(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut req = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if let Some(token) = &self.token {
+            req["token"] = json!(token);
+        }
+        self.writer.write_all(format!("{}\n", req).as_bytes())?;
+        loop {
+            let msg = self.read()?;
+            if msg.get("id").and_then(Value::as_u64) != Some(id) {
+                continue;
+            }
+            if let Some(err) = msg.get("error") {
+                bail!("{}", err.get("message").and_then(Value::as_str).unwrap_or("request failed"));
+            }
+            return Ok(msg.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    /// Wait for the next notification after `subscribe`: (method, params).
+    pub fn notification(&mut self) -> Result<(String, Value)> {
+        loop {
+            let msg = self.read()?;
+            if let Some(method) = msg.get("method").and_then(Value::as_str) {
+                let params = msg.get("params").cloned().unwrap_or(Value::Null);
+                return Ok((method.to_string(), params));
+            }
+        }
+    }
+
+    fn read(&mut self) -> Result<Value> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            bail!("finger closed the connection");
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(line: &str, token: Option<&str>) -> i64 {
+        parse_request(line, token).unwrap_err().1.code
+    }
+
+    #[test]
+    fn parses_requests_and_notifications() {
+        let req = parse_request(r#"{"jsonrpc":"2.0","id":3,"method":"enable","params":{"bot":"farm"}}"#, None).unwrap();
+        assert_eq!(req, Request { id: Some(json!(3)), method: "enable".into(), params: json!({ "bot": "farm" }) });
+        let note = parse_request(r#"{"jsonrpc":"2.0","method":"quit"}"#, None).unwrap();
+        assert_eq!((note.id, note.params), (None, Value::Null));
+    }
+
+    #[test]
+    fn rejects_what_is_not_json_rpc() {
+        assert_eq!(code("GET / HTTP/1.1", None), PARSE_ERROR);
+        assert_eq!(code("[1, 2]", None), INVALID_REQUEST);
+        assert_eq!(code(r#"{"id":1,"method":"list"}"#, None), INVALID_REQUEST);
+        assert_eq!(code(r#"{"jsonrpc":"1.0","id":1,"method":"list"}"#, None), INVALID_REQUEST);
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1,"method":7}"#, None), INVALID_REQUEST);
+        // The id is echoed when it could be read
+        let (id, _) = parse_request(r#"{"jsonrpc":"2.0","id":"a"}"#, None).unwrap_err();
+        assert_eq!(id, json!("a"));
+    }
+
+    #[test]
+    fn checks_the_token() {
+        let req = |token: &str| format!(r#"{{"jsonrpc":"2.0","id":1,"method":"list","token":"{}"}}"#, token);
+        assert!(parse_request(&req("s3cret"), Some("s3cret")).is_ok());
+        assert_eq!(code(&req("guess"), Some("s3cret")), UNAUTHORIZED);
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1,"method":"list"}"#, Some("s3cret")), UNAUTHORIZED);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn socket_is_private_and_drops_bad_clients() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("finger-ctl-{}-socket", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = endpoint(&dir);
+        let (cmd_tx, _cmd_rx) = mpsc::channel();
+        let (_log_tx, log_rx) = mpsc::channel();
+        let orch_state = Arc::new(Mutex::new(OrchestratorState::Stopped));
+        serve(&path, Arc::new(Mutex::new(Vec::new())), orch_state, cmd_tx, None, log_rx).unwrap();
+
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        // Nothing but the socket is left behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let mut client = Client::connect(&path).unwrap();
+        assert_eq!(client.call("list", Value::Null).unwrap()["state"], "stopped");
+
+        let mut raw = Stream::connect(&path).unwrap();
+        raw.write_all(b"GET / HTTP/1.1\n{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"list\"}\n").unwrap();
+        let replies: Vec<Value> = BufReader::new(raw).lines()
+            .map(|l| serde_json::from_str(&l.unwrap()).unwrap())
+            .collect();
+        assert_eq!(replies.len(), 1, "{:?}", replies);
+        assert_eq!(replies[0]["error"]["code"], PARSE_ERROR);
+
+        cleanup(&path);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,341 @@
+use std::f64::consts::PI;
+use std::sync::OnceLock;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::cancel;
+use crate::platform::{KeyPause, WindowHandle};
+use crate::sleep;
+use crate::types::*;
+
+static GLOBAL: OnceLock<HumanizeConfig> = OnceLock::new();
+
+/// Tuning for human-like input. Times are milliseconds, distances pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HumanizeConfig {
+    pub enabled: bool,
+    /// Clicks land uniformly within this radius of the target
+    pub scatter: f64,
+    /// Mean time to move the cursor to a click target
+    pub move_ms: f64,
+    /// How far paths bow sideways, as a fraction of their length
+    pub curve: f64,
+    /// Key press duration (mean, standard deviation)
+    pub hold_ms: f64,
+    pub hold_sd: f64,
+    /// Pause before each key press (mean, standard deviation)
+    pub gap_ms: f64,
+    pub gap_sd: f64,
+    /// Fixed random seed for reproducible input; random when unset. Each
+    /// instance mixes in its key, see `for_instance`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Default for HumanizeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scatter: 3.0,
+            move_ms: 250.0,
+            curve: 0.2,
+            hold_ms: 70.0,
+            hold_sd: 20.0,
+            gap_ms: 120.0,
+            gap_sd: 40.0,
+            seed: None,
+        }
+    }
+}
+
+impl HumanizeConfig {
+    /// Overlay the fields present in `overrides` (a bot's `humanize` table);
+    /// a bare boolean just switches the layer on or off.
+    pub fn merged(&self, overrides: &Value) -> Result<Self, String> {
+        let fields = match overrides {
+            Value::Object(fields) => fields,
+            Value::Bool(enabled) => return Ok(Self { enabled: *enabled, ..self.clone() }),
+            _ => return Err("humanize must be a table or a boolean".into()),
+        };
+        let mut base = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        if let Value::Object(map) = &mut base {
+            map.extend(fields.clone());
+        }
+        serde_json::from_value(base).map_err(|e| format!("humanize: {}", e))
+    }
+
+    /// The config for one instance: a fixed seed is combined with the
+    /// instance key, so instances of a bot move differently but each
+    /// replays the same input on every run.
+    pub fn for_instance(&self, key: &str) -> Self {
+        // FNV-1a, which unlike std's hasher is stable across Rust versions
+        let seed = self.seed.map(|seed| key.bytes().fold(seed ^ 0xcbf2_9ce4_8422_2325, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        }));
+        Self { seed, ..self.clone() }
+    }
+}
+
+/// Short pause between the key events around the main press
+const STEP_MS: f64 = 20.0;
+const STEP_SD: f64 = 6.0;
+
+/// Set the global defaults (from settings.json). Call once at startup.
+pub fn init(config: HumanizeConfig) {
+    GLOBAL.set(config).ok();
+}
+
+/// The global defaults, or the built-in ones if `init` was not called.
+pub fn global() -> HumanizeConfig {
+    GLOBAL.get().cloned().unwrap_or_default()
+}
+
+/// WindowHandle decorator that adds cursor paths, click scatter and key
+/// timing on top of the platform's raw input.
+pub struct Humanized {
+    inner: Box<dyn WindowHandle>,
+    config: HumanizeConfig,
+    rng: StdRng,
+    /// Last cursor position we moved to, as window ratios
+    cursor: Option<(f64, f64)>,
+}
+
+impl Humanized {
+    pub fn new(inner: Box<dyn WindowHandle>, config: HumanizeConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self { inner, config, rng, cursor: None }
+    }
+
+    fn normal(&mut self, mean: f64, sd: f64) -> f64 {
+        normal(&mut self.rng, mean, sd)
+    }
+
+    /// Move along a cubic Bezier from the last position to `(x, y)`, easing
+    /// in and out so the speed varies like a hand would.
+    fn move_to(&mut self, x: f64, y: f64, region: Region) {
+        let (w, h) = (region.w.max(1) as f64, region.h.max(1) as f64);
+        let (sx, sy) = self.cursor.unwrap_or_else(|| {
+            (self.rng.gen_range(0.3..0.7), self.rng.gen_range(0.3..0.7))
+        });
+        // Work in pixels so the curve is not squashed by the aspect ratio
+        let (p0, p3) = ((sx * w, sy * h), (x * w, y * h));
+        let (dx, dy) = (p3.0 - p0.0, p3.1 - p0.1);
+        let dist = (dx * dx + dy * dy).sqrt();
+        if dist < 1.0 {
+            return;
+        }
+        let (nx, ny) = (-dy / dist, dx / dist);
+        let bow1 = self.rng.gen_range(-1.0..1.0) * self.config.curve * dist;
+        let bow2 = self.rng.gen_range(-1.0..1.0) * self.config.curve * dist;
+        let p1 = (p0.0 + dx / 3.0 + nx * bow1, p0.1 + dy / 3.0 + ny * bow1);
+        let p2 = (p0.0 + dx * 2.0 / 3.0 + nx * bow2, p0.1 + dy * 2.0 / 3.0 + ny * bow2);
+
+        let steps = (dist / 25.0).clamp(6.0, 40.0) as usize;
+        let total = self.normal(self.config.move_ms, self.config.move_ms * 0.25);
+        let step_ms = (total / steps as f64).round() as u64;
+        for i in 1..=steps {
+            if cancel::requested() {
+                return;
+            }
+            let t = i as f64 / steps as f64;
+            let t = t * t * (3.0 - 2.0 * t);
+            let u = 1.0 - t;
+            let px = u * u * u * p0.0 + 3.0 * u * u * t * p1.0 + 3.0 * u * t * t * p2.0 + t * t * t * p3.0;
+            let py = u * u * u * p0.1 + 3.0 * u * u * t * p1.1 + 3.0 * u * t * t * p2.1 + t * t * t * p3.1;
+            self.inner.move_relative(px / w, py / h);
+            sleep::ms(step_ms);
+        }
+        self.cursor = Some((x, y));
+    }
+
+    /// Press `key` with every pause drawn from the seeded generator.
+    fn press(&mut self, key: &str) -> Result<(), InputError> {
+        let (config, rng) = (&self.config, &mut self.rng);
+        self.inner.tap_timed(key, &mut |pause| {
+            let (mean, sd) = match pause {
+                KeyPause::Lead => (config.gap_ms, config.gap_sd),
+                KeyPause::Hold => (config.hold_ms, config.hold_sd),
+                KeyPause::Modifier | KeyPause::Release => (STEP_MS, STEP_SD),
+            };
+            normal(rng, mean, sd).round() as u64
+        })
+    }
+}
+
+/// Normal sample (Box-Muller), kept above a fifth of the mean.
+fn normal(rng: &mut StdRng, mean: f64, sd: f64) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    let z = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+    (mean + sd * z).max(mean * 0.2)
+}
+
+impl WindowHandle for Humanized {
+    fn id(&self) -> WindowId { self.inner.id() }
+    fn title(&self) -> &str { self.inner.title() }
+    fn region(&self) -> Option<Region> { self.inner.region() }
+    fn update(&mut self) { self.inner.update() }
+    fn activate(&mut self) -> bool { self.inner.activate() }
+    fn is_foreground(&self) -> bool { self.inner.is_foreground() }
+
+    fn click_relative(&mut self, x_ratio: f64, y_ratio: f64) -> Result<(), InputError> {
+        let Some(region) = self.inner.region() else {
+            return self.inner.click_relative(x_ratio, y_ratio);
+        };
+        // Uniform point in a disk around the target
+        let r = self.config.scatter * self.rng.gen::<f64>().sqrt();
+        let theta = self.rng.gen_range(0.0..2.0 * PI);
+        let x = (x_ratio + r * theta.cos() / region.w.max(1) as f64).clamp(0.0, 1.0);
+        let y = (y_ratio + r * theta.sin() / region.h.max(1) as f64).clamp(0.0, 1.0);
+
+        self.move_to(x, y, region);
+        if cancel::requested() {
+            return Err(InputError::Cancelled);
+        }
+        self.inner.click_relative(x, y)
+    }
+
+    fn move_relative(&mut self, x_ratio: f64, y_ratio: f64) {
+        match self.inner.region() {
+            Some(region) => self.move_to(x_ratio, y_ratio, region),
+            None => self.inner.move_relative(x_ratio, y_ratio),
+        }
+    }
+
+    fn tap(&mut self, key: &str) -> Result<(), InputError> {
+        self.press(key)
+    }
+
+    fn tap_timed(&mut self, key: &str, pause: &mut dyn FnMut(KeyPause) -> u64) -> Result<(), InputError> {
+        self.inner.tap_timed(key, pause)
+    }
+
+    fn type_text(&mut self, text: &str) -> Result<(), InputError> {
+        for ch in text.chars() {
+            if cancel::requested() {
+                return Err(InputError::Cancelled);
+            }
+            // Key names are ASCII, and "+" joins modifiers; anything else is
+            // typed as text by the platform
+            if ch.is_ascii() && !ch.is_ascii_control() && ch != '+' {
+                self.press(&ch.to_string())?;
+                continue;
+            }
+            let gap = self.normal(self.config.gap_ms, self.config.gap_sd).round() as u64;
+            sleep::ms(gap);
+            if cancel::requested() {
+                return Err(InputError::Cancelled);
+            }
+            self.inner.type_text(&ch.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn capture(&mut self, rect: Option<CaptureRect>) -> Option<Capture> {
+        self.inner.capture(rect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Writes down every input and pause it is asked for
+    struct Logged {
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl WindowHandle for Logged {
+        fn id(&self) -> WindowId { 1 }
+        fn title(&self) -> &str { "Game" }
+        fn region(&self) -> Option<Region> {
+            Some(Region { l: 0, t: 0, r: 800, b: 600, w: 800, h: 600, cx: 400, cy: 300 })
+        }
+        fn update(&mut self) {}
+        fn activate(&mut self) -> bool { false }
+        fn is_foreground(&self) -> bool { true }
+
+        fn click_relative(&mut self, x_ratio: f64, y_ratio: f64) -> Result<(), InputError> {
+            self.log.lock().unwrap().push(format!("click {:.6} {:.6}", x_ratio, y_ratio));
+            Ok(())
+        }
+
+        fn move_relative(&mut self, x_ratio: f64, y_ratio: f64) {
+            self.log.lock().unwrap().push(format!("move {:.6} {:.6}", x_ratio, y_ratio));
+        }
+
+        fn tap(&mut self, key: &str) -> Result<(), InputError> {
+            self.log.lock().unwrap().push(format!("tap {}", key));
+            Ok(())
+        }
+
+        fn tap_timed(&mut self, key: &str, pause: &mut dyn FnMut(KeyPause) -> u64) -> Result<(), InputError> {
+            let steps = [KeyPause::Lead, KeyPause::Modifier, KeyPause::Hold, KeyPause::Release, KeyPause::Modifier];
+            let pauses: Vec<u64> = steps.into_iter().map(pause).collect();
+            self.log.lock().unwrap().push(format!("tap {} {:?}", key, pauses));
+            Ok(())
+        }
+
+        fn type_text(&mut self, text: &str) -> Result<(), InputError> {
+            self.log.lock().unwrap().push(format!("type {}", text));
+            Ok(())
+        }
+
+        fn capture(&mut self, _rect: Option<CaptureRect>) -> Option<Capture> { None }
+    }
+
+    fn run(config: &HumanizeConfig) -> Vec<String> {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut win = Humanized::new(Box::new(Logged { log: Arc::clone(&log) }), config.clone());
+        win.click_relative(0.5, 0.5).unwrap();
+        win.tap("ctrl+a").unwrap();
+        win.click_relative(0.1, 0.9).unwrap();
+        win.type_text("a+é").unwrap();
+        let log = log.lock().unwrap().clone();
+        log
+    }
+
+    fn seeded(seed: u64) -> HumanizeConfig {
+        // No cursor travel time and short gaps, so the test barely sleeps
+        HumanizeConfig { enabled: true, move_ms: 0.0, gap_ms: 2.0, gap_sd: 1.0, seed: Some(seed), ..Default::default() }
+    }
+
+    #[test]
+    fn same_seed_replays_offsets_and_delays() {
+        let first = run(&seeded(42));
+        assert!(first.iter().any(|l| l.starts_with("tap ctrl+a [")), "{:?}", first);
+        assert_eq!(first.iter().filter(|l| l.starts_with("tap ")).count(), 2);
+        // Characters that are not key names are typed, not tapped
+        assert_eq!(first.iter().filter(|l| l.starts_with("type ")).collect::<Vec<_>>(), ["type +", "type é"]);
+        assert_eq!(first, run(&seeded(42)));
+        assert_ne!(first, run(&seeded(43)));
+    }
+
+    #[test]
+    fn instances_get_their_own_seed() {
+        let config = seeded(42);
+        let (one, two) = (config.for_instance("bot#1"), config.for_instance("bot#2"));
+        assert_ne!(one.seed, two.seed);
+        assert_eq!(one, config.for_instance("bot#1"));
+        assert_eq!(run(&one), run(&config.for_instance("bot#1")));
+        assert_ne!(run(&one), run(&two));
+        assert_eq!(HumanizeConfig::default().for_instance("bot#1").seed, None);
+    }
+
+    #[test]
+    fn merged_overrides_fields() {
+        let base = HumanizeConfig::default();
+        let merged = base.merged(&serde_json::json!({ "scatter": 5, "seed": 7 })).unwrap();
+        assert_eq!((merged.scatter, merged.seed, merged.hold_ms), (5.0, Some(7), base.hold_ms));
+        assert!(base.merged(&serde_json::json!(true)).unwrap().enabled);
+        assert!(base.merged(&serde_json::json!("yes")).is_err());
+    }
+}
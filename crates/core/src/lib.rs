@@ -1,8 +1,19 @@
 pub mod types;
+pub mod bus;
+pub mod cancel;
+pub mod control;
 pub mod platform;
 pub mod hint;
+pub mod humanize;
 pub mod lua_rt;
 pub mod orchestrator;
+pub mod options;
+pub mod recorder;
+pub mod schedule;
+pub mod scheduler;
 pub mod logger;
 pub mod settings;
 pub mod sleep;
+pub mod store;
+pub mod watcher;
+pub mod worker;
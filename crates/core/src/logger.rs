@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::fs::{self, File, OpenOptions};
-use std::io::{Seek, SeekFrom, Write};
+use std::io::{self, Seek, SeekFrom, Write};
 use std::path::Path;
 use std::sync::{mpsc, Mutex, OnceLock};
 use chrono::Local;
@@ -8,7 +8,8 @@ use chrono::Local;
 static LOGGER: OnceLock<Mutex<Logger>> = OnceLock::new();
 
 struct Logger {
-    file: File,
+    /// None writes to stderr
+    file: Option<File>,
     tui_tx: Option<mpsc::Sender<String>>,
     prefixes: HashMap<String, u8>, // prefix -> color index
 }
@@ -29,7 +30,15 @@ pub fn init(log_dir: &Path) {
         .expect("failed to open log file");
 
     LOGGER
-        .set(Mutex::new(Logger { file, tui_tx: None, prefixes: HashMap::new() }))
+        .set(Mutex::new(Logger { file: Some(file), tui_tx: None, prefixes: HashMap::new() }))
+        .ok();
+}
+
+/// Initialize the global logger on stderr, for commands that must leave the
+/// log file of a finger running in the same folder alone.
+pub fn init_stderr() {
+    LOGGER
+        .set(Mutex::new(Logger { file: None, tui_tx: None, prefixes: HashMap::new() }))
         .ok();
 }
 
@@ -37,8 +46,10 @@ pub fn init(log_dir: &Path) {
 pub fn clear_file() {
     if let Some(logger) = LOGGER.get() {
         let mut l = logger.lock().unwrap();
-        l.file.set_len(0).ok();
-        l.file.seek(SeekFrom::Start(0)).ok();
+        if let Some(file) = &mut l.file {
+            file.set_len(0).ok();
+            file.seek(SeekFrom::Start(0)).ok();
+        }
     }
 }
 
@@ -73,7 +84,10 @@ fn write_log(level: &str, prefix: &str, color: u8, msg: &str) {
 
     if let Some(logger) = LOGGER.get() {
         let mut l = logger.lock().unwrap();
-        writeln!(l.file, "{}", file_line).ok();
+        match &mut l.file {
+            Some(file) => writeln!(file, "{}", file_line).ok(),
+            None => writeln!(io::stderr(), "{}", file_line).ok(),
+        };
         if let Some(tx) = &l.tui_tx {
             // First line: full structured entry
             let mut parts = msg.splitn(2, '\n');
@@ -89,6 +103,18 @@ fn write_log(level: &str, prefix: &str, color: u8, msg: &str) {
     }
 }
 
+/// Turn a channel entry back into the line written to the log file, for
+/// front ends that print the log instead of rendering it.
+pub fn plain_line(entry: &str) -> String {
+    let parts: Vec<&str> = entry.splitn(5, '\x1f').collect();
+    match parts.as_slice() {
+        ["_", _, _, _, msg] => msg.to_string(),
+        [level, "", _, ts, msg] => format!("[{}] [{}] {}", ts, level, msg),
+        [level, prefix, _, ts, msg] => format!("[{}] [{}] [{}] {}", ts, level, prefix, msg),
+        _ => entry.to_string(),
+    }
+}
+
 pub fn info(msg: &str) {
     write_log("INFO", "", 0, msg);
 }
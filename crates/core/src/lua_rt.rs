@@ -1,59 +1,120 @@
 use std::cell::{Cell, RefCell};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 use anyhow::{Result, anyhow};
 use mlua::prelude::*;
+use serde_json::{Map, Value};
 
 use crate::types::*;
 use crate::platform::WindowHandle;
 use crate::hint;
+use crate::humanize::{self, Humanized};
 use crate::sleep;
 use crate::logger;
+use crate::bus::{self, Message};
+use crate::cancel;
+use crate::options::{OptionSpec, OptionType};
+use crate::store::{self, Store};
+
+/// Error raised into Lua when a stop request aborts the running code
+const CANCELLED: &str = "cancelled";
+
+/// Refuse to start more input or Lua work once a stop was requested.
+fn check_cancel() -> mlua::Result<()> {
+    if cancel::requested() {
+        return Err(LuaError::runtime(CANCELLED));
+    }
+    Ok(())
+}
+
+/// Area win:decodev2 reads the hint from, relative to the window origin
+const HINT_RECT: CaptureRect = CaptureRect { l: 0, t: 0, w: 320, h: 80 };
 
 /// Wrapper around a WindowHandle for Lua userdata.
 struct LuaWindow {
     inner: Rc<RefCell<Box<dyn WindowHandle>>>,
     active: Rc<Cell<bool>>,
+    budget: Rc<Budget>,
+    /// Hint capture taken ahead of observe() by `LuaBot::capture_frame`
+    frame: Rc<RefCell<Option<Capture>>>,
+    /// Set when input found another window in front; on_focus_lost() is due
+    focus_lost: Rc<Cell<bool>>,
+}
+
+impl LuaWindow {
+    /// Send input to the window if it is active and in front. Returns true,
+    /// or false and the reason; a stop request still unwinds the tick.
+    fn input(
+        &self,
+        what: &str,
+        f: impl FnOnce(&mut dyn WindowHandle) -> Result<(), InputError>,
+    ) -> mlua::Result<(bool, Option<&'static str>)> {
+        if !self.active.get() {
+            logger::warn(&format!("dropped win:{} — window not active", what));
+            return Ok((false, Some(InputError::Inactive.as_str())));
+        }
+        check_cancel()?;
+        let result = self.budget.exclude(|| {
+            let mut win = self.inner.borrow_mut();
+            if !win.is_foreground() {
+                return Err(InputError::Unfocused);
+            }
+            f(win.as_mut())
+        });
+        match result {
+            Ok(()) => Ok((true, None)),
+            Err(InputError::Cancelled) => Err(LuaError::runtime(CANCELLED)),
+            Err(e) => {
+                if e == InputError::Unfocused {
+                    self.focus_lost.set(true);
+                }
+                Ok((false, Some(e.as_str())))
+            }
+        }
+    }
 }
 
 impl LuaUserData for LuaWindow {
     fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
         methods.add_method("click", |_, this, (x_ratio, y_ratio): (f64, f64)| {
-            if !this.active.get() {
-                logger::warn("dropped win:click — window not active");
-                return Ok(());
-            }
-            this.inner.borrow_mut().click_relative(x_ratio, y_ratio);
-            Ok(())
+            this.input("click", |win| win.click_relative(x_ratio, y_ratio))
         });
 
         methods.add_method("tap", |_, this, key: String| {
-            if !this.active.get() {
-                logger::warn("dropped win:tap — window not active");
-                return Ok(());
-            }
-            this.inner.borrow_mut().tap(&key);
-            Ok(())
+            this.input("tap", |win| win.tap(&key))
         });
 
         methods.add_method("type", |_, this, text: String| {
-            if !this.active.get() {
-                logger::warn("dropped win:type — window not active");
-                return Ok(());
+            this.input("type", |win| win.type_text(&text))
+        });
+
+        methods.add_method("id", |_, this, ()| {
+            Ok(this.inner.borrow().id())
+        });
+
+        methods.add_method("title", |_, this, ()| {
+            Ok(this.inner.borrow().title().to_string())
+        });
+
+        methods.add_method("region", |_, this, ()| {
+            match this.inner.borrow().region() {
+                Some(r) => Ok((Some(r.l), Some(r.t), Some(r.w), Some(r.h))),
+                None => Ok((None, None, None, None)),
             }
-            this.inner.borrow_mut().type_text(&text);
-            Ok(())
         });
 
+        // Captures work on background windows, so unlike input this does
+        // not need the window active
         methods.add_method("decodev2", |lua, this, ()| {
-            if !this.active.get() {
-                logger::warn("dropped win:decodev2 — window not active");
-                return Ok(LuaNil);
-            }
-            let rect = Some(CaptureRect { l: 0, t: 0, w: 320, h: 80 });
-            let capture = this.inner.borrow_mut().capture(rect);
+            check_cancel()?;
+            let frame = this.frame.borrow_mut().take();
+            let capture = frame.or_else(|| {
+                this.budget.exclude(|| this.inner.borrow_mut().capture(Some(HINT_RECT)))
+            });
             match capture {
                 Some(cap) => match hint::decode_hint_v2(&cap) {
                     Some(segments) => {
@@ -71,14 +132,284 @@ impl LuaUserData for LuaWindow {
     }
 }
 
+/// Shared library root on every bot's package.path (e.g. `bots/lib`)
+static LIB_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Set the shared library root. Call once at startup, before loading bots.
+pub fn set_lib_dir(dir: &Path) {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    LIB_DIR.set(dir).ok();
+}
+
+/// The shared library root, if one was set.
+pub fn lib_dir() -> Option<&'static Path> {
+    LIB_DIR.get().map(PathBuf::as_path)
+}
+
+/// Point require() at the bot's own directory first, then the shared library.
+fn set_package_path(lua: &Lua, script_path: &Path) -> mlua::Result<()> {
+    let mut dirs: Vec<&Path> = script_path.parent().into_iter().collect();
+    dirs.extend(lib_dir());
+    let path = dirs.iter()
+        .map(|d| {
+            let d = d.to_string_lossy();
+            format!("{}/?.lua;{}/?/init.lua", d, d)
+        })
+        .collect::<Vec<_>>()
+        .join(";");
+    lua.globals().get::<LuaTable>("package")?.set("path", path)
+}
+
+/// Cooldown used when tick() returns nothing (or garbage)
+const DEFAULT_COOLDOWN: f64 = 5.0;
+
+/// Wall time one activation may spend in Lua unless the bot sets `tick_budget`
+const DEFAULT_BUDGET: f64 = 5.0;
+
+/// Seconds one step of a run may take before the watchdog flags the
+/// instance as stuck, unless the bot sets `stuck_after`
+const DEFAULT_STUCK_AFTER: f64 = 60.0;
+
+/// VM memory cap in MB unless the bot sets `memory_limit`
+const DEFAULT_MEMORY_MB: f64 = 128.0;
+
+/// Settle time after focus moves to the bot's window, unless it sets
+/// `activation_delay`
+const DEFAULT_ACTIVATION_DELAY: f64 = 0.5;
+
+/// With `verify_activation`, how long to wait for the window to reach the
+/// foreground before the tick is skipped
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Wall time stop() may take, waits included, so cleanup cannot hold up
+/// stopping or quitting
+const STOP_BUDGET: Duration = Duration::from_secs(2);
+
+/// How often (in VM instructions) the budget hook checks the clock
+const BUDGET_CHECK_EVERY: u32 = 10_000;
+
+/// Where a bot's Lua code is, for the stuck-tick watchdog. Readable from
+/// other threads while the bot's own thread is blocked.
+#[derive(Default)]
+pub struct Trace {
+    stack: Mutex<Option<String>>,
+    /// Ask the budget hook to record the stack the next time it runs
+    wanted: AtomicBool,
+}
+
+impl Trace {
+    /// The Lua stack as of the blocking call in progress or the last
+    /// requested sample, if any.
+    pub fn stack(&self) -> Option<String> {
+        self.stack.lock().unwrap().clone()
+    }
+
+    /// Have the running Lua code record its stack, for when it is busy in
+    /// Lua rather than blocked in a Rust call.
+    pub fn request(&self) {
+        self.wanted.store(true, Ordering::Release);
+    }
+
+    fn set(&self, stack: Option<String>) {
+        *self.stack.lock().unwrap() = stack;
+    }
+}
+
+/// The Lua call stack of the code calling into Rust, innermost first, one
+/// `source:line: in name` line per frame. None outside Lua code.
+fn traceback(lua: &Lua) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut level = 0;
+    while let Some(frame) = lua.inspect_stack(level) {
+        level += 1;
+        let source = frame.source();
+        let src = source.short_src.as_deref().unwrap_or("?");
+        let at = match frame.curr_line() {
+            line if line > 0 => format!("{}:{}", src, line),
+            _ => src.to_string(),
+        };
+        let names = frame.names();
+        let name = match (names.name.as_deref(), source.what) {
+            (Some(name), _) => format!("{} '{}'", names.name_what.unwrap_or("function"), name),
+            (None, "main") => "main chunk".to_string(),
+            (None, _) => format!("function <{}:{}>", src, source.line_defined.unwrap_or(0)),
+        };
+        lines.push(format!("{}: in {}", at, name));
+    }
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// Wall-time budget for Lua code, enforced from an instruction-count hook.
+/// Time spent blocked in Rust (window input, F.delay) is not charged,
+/// except while `waits` is set.
+struct Budget {
+    limit: Cell<Duration>,
+    deadline: Cell<Option<Instant>>,
+    /// What is running, for the error message ("tick", "start", …)
+    what: Cell<&'static str>,
+    /// Charge blocking calls too, and cut F.delay short at the deadline
+    waits: Cell<bool>,
+    lua: WeakLua,
+    trace: RefCell<Arc<Trace>>,
+}
+
+impl Budget {
+    fn new(lua: &Lua) -> Rc<Self> {
+        Rc::new(Self {
+            limit: Cell::new(Duration::from_secs_f64(DEFAULT_BUDGET)),
+            deadline: Cell::new(None),
+            what: Cell::new("tick"),
+            waits: Cell::new(false),
+            lua: lua.weak(),
+            trace: RefCell::new(Arc::default()),
+        })
+    }
+
+    /// Hook `co` (the main state if None) and start the clock unless it is
+    /// already running. mlua only keeps one hooked thread at a time, so this
+    /// is called again before every call into the VM.
+    fn arm(self: &Rc<Self>, lua: &Lua, co: Option<&LuaThread>, what: &'static str) {
+        let triggers = LuaHookTriggers::new().every_nth_instruction(BUDGET_CHECK_EVERY);
+        let budget = Rc::clone(self);
+        let hook = move |lua: &Lua, _: mlua::Debug| {
+            check_cancel()?;
+            let trace = budget.trace.borrow();
+            if trace.wanted.swap(false, Ordering::AcqRel) {
+                trace.set(traceback(lua));
+            }
+            match budget.deadline.get() {
+                Some(at) if Instant::now() > at => Err(budget.exceeded()),
+                _ => Ok(LuaVmState::Continue),
+            }
+        };
+        match co {
+            Some(co) => co.set_hook(triggers, hook),
+            None => lua.set_hook(triggers, hook),
+        }
+        if self.deadline.get().is_none() {
+            self.what.set(what);
+            self.deadline.set(Some(Instant::now() + self.limit.get()));
+        }
+    }
+
+    fn exceeded(&self) -> LuaError {
+        LuaError::runtime(format!(
+            "{} exceeded budget of {}s", self.what.get(), self.limit.get().as_secs_f64()
+        ))
+    }
+
+    fn disarm(&self) {
+        self.deadline.set(None);
+        // A sample taken by the hook is stale once the call returns
+        self.trace.borrow().set(None);
+    }
+
+    /// Run a blocking Rust call without charging its time to the budget.
+    /// The Lua stack that made the call is kept in the trace meanwhile.
+    fn exclude<T>(&self, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let trace = Arc::clone(&self.trace.borrow());
+        trace.set(self.lua.try_upgrade().and_then(|lua| traceback(&lua)));
+        let out = f();
+        trace.set(None);
+        match self.deadline.get() {
+            Some(at) if !self.waits.get() => self.deadline.set(Some(at + start.elapsed())),
+            _ => {}
+        }
+        out
+    }
+
+    /// Milliseconds left when waits are charged; a longer F.delay is cut
+    /// to this.
+    fn wait_left(&self) -> Option<u64> {
+        let at = self.deadline.get().filter(|_| self.waits.get())?;
+        Some(at.saturating_duration_since(Instant::now()).as_millis() as u64)
+    }
+}
+
+/// Apply the bot's `tick_budget` (seconds) and `memory_limit` (MB) fields.
+fn apply_limits(lua: &Lua, table: &LuaTable, budget: &Budget) -> mlua::Result<()> {
+    let secs = table.get::<Option<f64>>("tick_budget")?.unwrap_or(DEFAULT_BUDGET);
+    let mb = table.get::<Option<f64>>("memory_limit")?.unwrap_or(DEFAULT_MEMORY_MB);
+    if !(secs.is_finite() && secs > 0.0) {
+        return Err(LuaError::runtime("tick_budget must be a positive number of seconds"));
+    }
+    if !(mb.is_finite() && mb > 0.0) {
+        return Err(LuaError::runtime("memory_limit must be a positive number of MB"));
+    }
+    budget.limit.set(Duration::from_secs_f64(secs));
+    lua.set_memory_limit((mb * 1024.0 * 1024.0) as usize)?;
+    Ok(())
+}
+
+/// How a bot's window is brought to the front before each tick
+#[derive(Clone, Copy)]
+struct Activation {
+    delay: Duration,
+    verify: bool,
+}
+
+/// Read the bot's `activation_delay` (seconds) and `verify_activation` fields.
+fn read_activation(table: &LuaTable) -> mlua::Result<Activation> {
+    let secs = table.get::<Option<f64>>("activation_delay")?.unwrap_or(DEFAULT_ACTIVATION_DELAY);
+    if !(secs.is_finite() && secs >= 0.0) {
+        return Err(LuaError::runtime("activation_delay must be a non-negative number of seconds"));
+    }
+    Ok(Activation {
+        delay: Duration::from_secs_f64(secs),
+        verify: table.get::<Option<bool>>("verify_activation")?.unwrap_or(false),
+    })
+}
+
+/// Wrap the window in the humanization layer when the global settings or the
+/// bot's `humanize` table turn it on; the table overrides individual fields.
+/// A fixed seed is made per instance from `key`.
+fn apply_humanize(
+    lua: &Lua,
+    table: &LuaTable,
+    win_handle: Box<dyn WindowHandle>,
+    key: &str,
+) -> mlua::Result<Box<dyn WindowHandle>> {
+    let mut config = humanize::global();
+    if let Some(overrides) = table.get::<Option<LuaValue>>("humanize")? {
+        let overrides: Value = lua.from_value(overrides)?;
+        config = config.merged(&overrides).map_err(LuaError::runtime)?;
+    }
+    if !config.enabled {
+        return Ok(win_handle);
+    }
+    Ok(Box::new(Humanized::new(win_handle, config.for_instance(key))))
+}
+
+/// Registry names of the runtime hooks defined in `register_globals`
+const FIRE_TIMERS: &str = "finger.fire_timers";
+const NEXT_TIMER: &str = "finger.next_timer";
+const FSM_STATUS: &str = "finger.fsm_status";
+const HAS_SUBSCRIBER: &str = "finger.has_subscriber";
+const DISPATCH: &str = "finger.dispatch";
+
 /// A loaded Lua bot instance, owning its own Lua VM.
 pub struct LuaBot {
     lua: Lua,
+    info: InstanceInfo,
     bot_key: LuaRegistryKey,
     win: Rc<RefCell<Box<dyn WindowHandle>>>,
     active: Rc<Cell<bool>>,
+    budget: Rc<Budget>,
+    frame: Rc<RefCell<Option<Capture>>>,
+    focus_lost: Rc<Cell<bool>>,
+    /// Window size last reported to on_resize(), or seen at start
+    size: Option<(i32, i32)>,
+    activation: Activation,
     on_error: Arc<dyn Fn(Vec<String>) + Send + Sync>,
     suspended: Option<LuaRegistryKey>,
+    /// When tick() (or the parked coroutine) is next due
+    wake_at: Option<Instant>,
+    /// Bus messages waiting for their F.subscribe handlers
+    inbox: Vec<Message>,
+    /// Actions requested from the TUI, run on the next activation
+    actions: Vec<String>,
+    stores: Vec<Arc<Mutex<Store>>>,
 }
 
 /// Format an mlua runtime error: strip `[string "…"]` wrappers and
@@ -126,25 +457,26 @@ fn lua_err(e: mlua::Error) -> anyhow::Error {
 }
 
 impl LuaBot {
-    /// Load a bot script just to extract metadata (window_pattern, description).
+    /// Load a bot script just to extract metadata (window_pattern, description, options).
     /// Does NOT call start(). Used during bot discovery.
-    pub fn load_meta(path: &Path) -> Result<(String, String)> {
+    pub fn load_meta(path: &Path) -> Result<BotMeta> {
         let lua = Lua::new();
-        register_globals(&lua, "").map_err(lua_err)?;
+        let budget = Budget::new(&lua);
+        register_globals(&lua, "", &budget).map_err(lua_err)?;
+        lua.set_memory_limit((DEFAULT_MEMORY_MB * 1024.0 * 1024.0) as usize).map_err(lua_err)?;
 
-        // Set package.path so require() finds modules in the bot's directory
-        if let Some(bot_dir) = path.parent() {
-            let dir_str = bot_dir.to_string_lossy();
-            let pkg: LuaTable = lua.globals().get("package").map_err(lua_err)?;
-            pkg.set("path", format!("{}/?.lua;{}/?/init.lua", dir_str, dir_str)).map_err(lua_err)?;
-        }
+        set_package_path(&lua, path).map_err(lua_err)?;
 
         let code = std::fs::read_to_string(path)?;
+        budget.arm(&lua, None, "loading");
         let table: LuaTable = lua
             .load(&code)
             .set_name(chunk_name(path))
             .eval()
             .map_err(lua_err)?;
+        budget.disarm();
+        apply_limits(&lua, &table, &budget).map_err(lua_err)?;
+        read_activation(&table).map_err(lua_err)?;
 
         let pattern: String = table.get("window_pattern").map_err(lua_err)?;
         let description: String = table.get("description").map_err(lua_err)?;
@@ -152,58 +484,240 @@ impl LuaBot {
         // Validate tick exists
         let _: LuaFunction = table.get("tick").map_err(lua_err)?;
 
-        Ok((pattern, description))
+        let options = match table.get::<Option<LuaTable>>("options").map_err(lua_err)? {
+            Some(t) => parse_options(&lua, t)?,
+            None => Vec::new(),
+        };
+
+        let restart = match table.get::<Option<String>>("restart").map_err(lua_err)? {
+            Some(name) => {
+                let max = table.get::<Option<u32>>("max_restarts").map_err(lua_err)?;
+                RestartPolicy::parse(&name, max).ok_or_else(|| anyhow!(
+                    "restart must be \"never\", \"on-error\" or \"always\", got \"{}\"", name
+                ))?
+            }
+            None => RestartPolicy::default(),
+        };
+
+        let actions = match table.get::<Option<LuaTable>>("actions").map_err(lua_err)? {
+            Some(t) => parse_actions(t)?,
+            None => Vec::new(),
+        };
+
+        let priority = table.get::<Option<i32>>("priority").map_err(lua_err)?.unwrap_or(0);
+
+        let stuck_after = table.get::<Option<f64>>("stuck_after").map_err(lua_err)?.unwrap_or(DEFAULT_STUCK_AFTER);
+        if !(stuck_after.is_finite() && stuck_after > 0.0) {
+            return Err(anyhow!("stuck_after must be a positive number of seconds"));
+        }
+        let stuck_after = Duration::from_secs_f64(stuck_after);
+
+        Ok(BotMeta { window_pattern: pattern, description, options, restart, actions, priority, stuck_after })
     }
 
     /// Create a new LuaBot, load the script, and call start(win).
     pub fn new(
         script_path: &Path,
-        instance_id: &str,
+        info: InstanceInfo,
         win_handle: Box<dyn WindowHandle>,
+        opts: Map<String, Value>,
         on_error: Arc<dyn Fn(Vec<String>) + Send + Sync>,
     ) -> Result<Self> {
+        let on_err = Arc::clone(&on_error);
+        Self::create(script_path, info, win_handle, opts, on_error, None)
+            .map_err(|e| { on_err(format_mlua_error(&e)); lua_err(e) })
+    }
+
+    /// Build the VM, load the script and call start(win, opts, snapshot).
+    /// Errors are returned to the caller; on_error is not fired here.
+    fn create(
+        script_path: &Path,
+        info: InstanceInfo,
+        win_handle: Box<dyn WindowHandle>,
+        opts: Map<String, Value>,
+        on_error: Arc<dyn Fn(Vec<String>) + Send + Sync>,
+        snapshot: Option<Value>,
+    ) -> mlua::Result<Self> {
         let lua = Lua::new();
-        register_globals(&lua, instance_id).map_err(lua_err)?;
+        let (bot_name, instance_id) = (info.bot.as_str(), info.id.as_str());
+        let budget = Budget::new(&lua);
+        register_globals(&lua, instance_id, &budget)?;
+        lua.set_memory_limit((DEFAULT_MEMORY_MB * 1024.0 * 1024.0) as usize)?;
+        register_instance(&lua, &info)?;
 
-        // Set package.path so require() finds modules in the bot's directory
-        if let Some(bot_dir) = script_path.parent() {
-            let dir_str = bot_dir.to_string_lossy();
-            let pkg: LuaTable = lua.globals().get("package").map_err(lua_err)?;
-            pkg.set("path", format!("{}/?.lua;{}/?/init.lua", dir_str, dir_str)).map_err(lua_err)?;
-        }
+        // F.store is shared by all instances of the bot; F.store.instance is
+        // per instance slot, so it survives the game restarting
+        let bot_store = store::open(&format!("{}/store", bot_name));
+        let inst_store = store::open(&format!("{}/{}", bot_name, info.key));
+        let f_store = store_table(&lua, &bot_store)?;
+        f_store.set("instance", store_table(&lua, &inst_store)?)?;
+        lua.globals().get::<LuaTable>("F")?.set("store", f_store)?;
+        register_bus(&lua, bot_name, instance_id)?;
 
-        let code = std::fs::read_to_string(script_path)?;
-        let on_err = Arc::clone(&on_error);
-        let table: LuaTable = lua
+        set_package_path(&lua, script_path)?;
+
+        let code = std::fs::read_to_string(script_path).map_err(LuaError::external)?;
+        budget.arm(&lua, None, "loading");
+        let table = lua
             .load(&code)
             .set_name(chunk_name(script_path))
-            .eval()
-            .map_err(|e| { on_err(format_mlua_error(&e)); lua_err(e) })?;
+            .eval::<LuaTable>();
+        budget.disarm();
+        let table = table?;
+        apply_limits(&lua, &table, &budget)?;
+        let activation = read_activation(&table)?;
+        let win_handle = apply_humanize(&lua, &table, win_handle, &info.key)?;
 
-        let bot_key = lua.create_registry_value(table.clone()).map_err(lua_err)?;
+        let bot_key = lua.create_registry_value(table.clone())?;
 
         let win = Rc::new(RefCell::new(win_handle));
         let active = Rc::new(Cell::new(false));
+        let frame = Rc::new(RefCell::new(None));
+        let focus_lost = Rc::new(Cell::new(false));
+        let size = win.borrow().region().map(|r| (r.w, r.h));
 
-        // Create win userdata and call start(win)
+        // Create win userdata and call start(win, opts, snapshot)
         let win_ud = lua.create_userdata(LuaWindow {
             inner: Rc::clone(&win),
             active: Rc::clone(&active),
-        }).map_err(lua_err)?;
+            budget: Rc::clone(&budget),
+            frame: Rc::clone(&frame),
+            focus_lost: Rc::clone(&focus_lost),
+        })?;
 
         if let Ok(start_fn) = table.get::<LuaFunction>("start") {
-            let on_err = Arc::clone(&on_error);
-            start_fn.call::<()>(win_ud)
-                .map_err(|e| { on_err(format_mlua_error(&e)); lua_err(e) })?;
+            let opts = lua.to_value(&opts)?;
+            let snap = match &snapshot {
+                Some(v) => lua.to_value(v)?,
+                None => LuaNil,
+            };
+            budget.arm(&lua, None, "start");
+            let result = start_fn.call::<()>((win_ud, opts, snap));
+            budget.disarm();
+            result?;
         }
 
-        Ok(Self { lua, bot_key, win, active, on_error, suspended: None })
+        Ok(Self {
+            lua,
+            info,
+            bot_key,
+            win,
+            active,
+            budget,
+            frame,
+            focus_lost,
+            size,
+            activation,
+            on_error,
+            suspended: None,
+            wake_at: None,
+            inbox: Vec::new(),
+            actions: Vec::new(),
+            stores: vec![bot_store, inst_store],
+        })
     }
 
-    /// Call tick() -> Option<cooldown_s>. Fires on_error on runtime failure.
-    /// If a previous tick yielded (F.sleep), resumes the parked coroutine.
-    /// Otherwise creates a fresh coroutine wrapping tick().
-    pub fn tick(&mut self) -> Result<Option<f64>> {
+    /// Hot-swap the VM: call snapshot() on the old VM, rebuild from the current
+    /// script and pass the snapshot to start(win, opts, snapshot), then call
+    /// stop() on the old VM. Queued messages and actions carry over; a tick
+    /// parked in F.sleep does not.
+    /// On any failure the old VM is kept untouched and the error returned.
+    pub fn reload(
+        &mut self,
+        script_path: &Path,
+        win_handle: Box<dyn WindowHandle>,
+        opts: Map<String, Value>,
+    ) -> Result<()> {
+        let snapshot = self.snapshot()?;
+        let mut next = Self::create(
+            script_path, self.info.clone(), win_handle, opts,
+            Arc::clone(&self.on_error), snapshot,
+        ).map_err(|e| anyhow!("{}", format_mlua_error(&e).join("\n  ")))?;
+        next.set_trace(self.trace());
+        next.inbox = std::mem::take(&mut self.inbox);
+        next.actions = std::mem::take(&mut self.actions);
+        let mut old = std::mem::replace(self, next);
+        if old.suspended.is_some() {
+            logger::warn_p(&self.info.id, "reload dropped the tick waiting in F.sleep; tick() starts over");
+        }
+        logger::info_p(&self.info.id, "reload reset the instance's state, except for what snapshot() kept");
+        // Failures are logged by stop() and cannot undo the swap
+        old.stop().ok();
+        Ok(())
+    }
+
+    /// Call the optional snapshot() hook and convert its result to JSON.
+    fn snapshot(&self) -> Result<Option<Value>> {
+        let table: LuaTable = self.lua.registry_value(&self.bot_key).map_err(lua_err)?;
+        match table.get::<LuaFunction>("snapshot") {
+            Ok(f) => {
+                let val: LuaValue = self.budgeted("snapshot", || f.call(())).map_err(lua_err)?;
+                if val.is_nil() {
+                    return Ok(None);
+                }
+                let json = self.lua.from_value(val)
+                    .map_err(|e| anyhow!("snapshot() returned a value that cannot cross VMs: {}", e))?;
+                Ok(Some(json))
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Run F.subscribe handlers for queued messages, queued actions and due
+    /// F.every/F.after timers, then call tick() -> cooldown_s if it is due.
+    /// If a previous tick yielded (F.sleep), resumes the parked coroutine instead
+    /// of creating a fresh one wrapping tick(). Fires on_error on runtime failure.
+    /// Returns seconds until tick() (or the parked coroutine) is due again.
+    /// Lua time in one call is capped by the tick budget.
+    pub fn tick(&mut self) -> Result<f64> {
+        let result = self.run_tick();
+        self.budget.disarm();
+        if self.active.get() && !self.win.borrow().is_foreground() {
+            self.focus_lost.set(true);
+        }
+        result
+    }
+
+    fn run_tick(&mut self) -> Result<f64> {
+        self.budget.arm(&self.lua, None, "tick");
+
+        if !self.inbox.is_empty() {
+            let dispatch: LuaFunction = self.lua.named_registry_value(DISPATCH).map_err(lua_err)?;
+            for msg in std::mem::take(&mut self.inbox) {
+                let value = self.lua.to_value(&msg.value).map_err(lua_err)?;
+                dispatch.call::<()>((msg.scope, msg.topic, value, msg.from))
+                    .map_err(|e| self.fail(e))?;
+            }
+        }
+
+        for name in std::mem::take(&mut self.actions) {
+            let table: LuaTable = self.lua.registry_value(&self.bot_key).map_err(lua_err)?;
+            let action = table.get::<Option<LuaTable>>("actions")
+                .and_then(|t| t.map(|t| t.get::<Option<LuaTable>>(name.as_str())).transpose())
+                .map_err(lua_err)?
+                .flatten();
+            let Some(action) = action else {
+                logger::warn_p(&self.info.id, &format!("no action \"{}\"", name));
+                continue;
+            };
+            logger::info_p(&self.info.id, &format!("action {}", name));
+            action.get::<LuaFunction>("fn")
+                .and_then(|f| f.call::<()>(()))
+                .map_err(|e| self.fail(e))?;
+        }
+
+        let fire: LuaFunction = self.lua.named_registry_value(FIRE_TIMERS).map_err(lua_err)?;
+        fire.call::<()>(())
+            .map_err(|e| self.fail(e))?;
+
+        // Woken early for a timer: the main flow is not due yet
+        if let Some(at) = self.wake_at {
+            let now = Instant::now();
+            if now < at {
+                return Ok((at - now).as_secs_f64());
+            }
+        }
+
         let co = if let Some(key) = self.suspended.take() {
             // Resume a parked coroutine from a previous F.sleep yield
             self.lua.registry_value(&key).map_err(lua_err)?
@@ -214,41 +728,170 @@ impl LuaBot {
             self.lua.create_thread(tick_fn).map_err(lua_err)?
         };
 
+        self.budget.arm(&self.lua, Some(&co), "tick");
         let result: LuaMultiValue = co.resume(())
-            .map_err(|e| { (self.on_error)(format_mlua_error(&e)); lua_err(e) })?;
+            .map_err(|e| self.fail(e))?;
 
-        match co.status() {
+        let first = result.iter().next().and_then(|v| match v {
+            LuaValue::Number(n) => Some(*n),
+            LuaValue::Integer(n) => Some(*n as f64),
+            _ => None,
+        });
+        let secs = match co.status() {
             LuaThreadStatus::Resumable => {
                 // F.sleep yielded — first value is the sleep seconds
-                let secs = result.iter().next().and_then(|v| match v {
-                    LuaValue::Number(n) => Some(*n),
-                    LuaValue::Integer(n) => Some(*n as f64),
-                    _ => None,
-                }).unwrap_or(1.0);
                 self.suspended = Some(self.lua.create_registry_value(co).map_err(lua_err)?);
-                Ok(Some(secs))
-            }
-            _ => {
-                // tick() returned normally
-                let val = result.iter().next();
-                match val {
-                    Some(LuaValue::Integer(s)) => Ok(Some(*s as f64)),
-                    Some(LuaValue::Number(s)) => Ok(Some(*s)),
-                    _ => Ok(None),
-                }
+                first.unwrap_or(1.0)
             }
+            // tick() returned normally
+            _ => first.unwrap_or(DEFAULT_COOLDOWN),
+        };
+        let secs = if secs.is_finite() && secs >= 0.0 { secs } else { DEFAULT_COOLDOWN };
+        self.wake_at = Some(Instant::now() + Duration::from_secs_f64(secs));
+        Ok(secs)
+    }
+
+    /// Whether the bot's next run can start with observe() instead of a
+    /// foreground tick: it exports one, and has no parked F.sleep, queued
+    /// messages or actions, or due timers, which all need tick().
+    pub fn observes(&self) -> bool {
+        let has_observe = self.lua.registry_value::<LuaTable>(&self.bot_key)
+            .and_then(|t| t.get::<Option<LuaFunction>>("observe"))
+            .is_ok_and(|f| f.is_some());
+        has_observe
+            && self.suspended.is_none()
+            && self.inbox.is_empty()
+            && self.actions.is_empty()
+            && !matches!(self.next_timer(), Some(t) if t <= 0.0)
+    }
+
+    /// Where the bot's Lua code is, shared with the watchdog. Kept across reloads.
+    pub fn trace(&self) -> Arc<Trace> {
+        Arc::clone(&self.budget.trace.borrow())
+    }
+
+    fn set_trace(&self, trace: Arc<Trace>) {
+        *self.budget.trace.borrow_mut() = trace;
+    }
+
+    /// Take the hint frame for the next observe() call. Nothing is activated.
+    pub fn capture_frame(&self) {
+        let frame = self.win.borrow_mut().capture(Some(HINT_RECT));
+        *self.frame.borrow_mut() = frame;
+    }
+
+    /// Call observe() with the window left in the background. Returns None
+    /// when it returned true, asking for tick() to run in the foreground;
+    /// otherwise the seconds until the bot wants to observe again.
+    pub fn observe(&mut self) -> Result<Option<f64>> {
+        let table: LuaTable = self.lua.registry_value(&self.bot_key).map_err(lua_err)?;
+        let observe: LuaFunction = table.get("observe").map_err(lua_err)?;
+        let result = self.budgeted("observe", || observe.call::<LuaValue>(()));
+        // A frame observe() did not read is stale by the next run
+        self.frame.borrow_mut().take();
+        let secs = match result.map_err(|e| self.fail(e))? {
+            LuaValue::Boolean(true) => return Ok(None),
+            LuaValue::Number(n) => n,
+            LuaValue::Integer(n) => n as f64,
+            _ => DEFAULT_COOLDOWN,
+        };
+        let secs = if secs.is_finite() && secs >= 0.0 { secs } else { DEFAULT_COOLDOWN };
+        self.wake_at = Some(Instant::now() + Duration::from_secs_f64(secs));
+        Ok(Some(secs))
+    }
+
+    /// Report a runtime error through on_error, unless it is a tick unwinding
+    /// after a stop request: that is not a failure of the bot.
+    fn fail(&self, e: mlua::Error) -> anyhow::Error {
+        if cancel::requested() {
+            logger::info_p(&self.info.id, "tick cancelled");
+        } else {
+            (self.on_error)(format_mlua_error(&e));
+        }
+        lua_err(e)
+    }
+
+    /// Queue a bus message if this VM subscribed to it. Returns true if queued;
+    /// the bot is then due at once so the handlers, and tick() unless it is
+    /// parked in F.sleep, run on its next activation.
+    pub fn deliver(&mut self, msg: &Message) -> bool {
+        if msg.from == self.info.id || (msg.scope != self.info.bot && msg.scope != bus::GLOBAL) {
+            return false;
+        }
+        let subscribed = self.lua.named_registry_value::<LuaFunction>(HAS_SUBSCRIBER)
+            .and_then(|f| f.call::<bool>((msg.scope.as_str(), msg.topic.as_str())))
+            .unwrap_or(false);
+        if !subscribed {
+            return false;
+        }
+        self.inbox.push(msg.clone());
+        self.wake();
+        true
+    }
+
+    /// Queue a bot action to run, with the window active, on the next
+    /// activation; the bot is due at once like for bus messages.
+    pub fn queue_action(&mut self, name: &str) {
+        self.actions.push(name.to_string());
+        self.wake();
+    }
+
+    /// Make the bot due now unless it is parked in F.sleep.
+    fn wake(&mut self) {
+        if self.suspended.is_none() {
+            self.wake_at = None;
+        }
+    }
+
+    /// Update F.instance.count after sibling windows appeared or went away.
+    pub fn set_instance_count(&mut self, count: usize) {
+        if self.info.count == count {
+            return;
+        }
+        self.info.count = count;
+        let instance = self.lua.globals().get::<LuaTable>("F")
+            .and_then(|f| f.get::<LuaTable>("instance"));
+        if let Ok(instance) = instance {
+            instance.set("count", count).ok();
         }
     }
 
-    /// Call get_status() -> String
-    pub fn get_status(&self) -> Result<String> {
+    /// Module files this VM has require()d, canonicalized, so hot reload
+    /// can tell which bots a changed library file affects.
+    pub fn required_files(&self) -> Vec<PathBuf> {
+        let Ok(package) = self.lua.globals().get::<LuaTable>("package") else { return Vec::new() };
+        let (Ok(loaded), Ok(search), Ok(path)) = (
+            package.get::<LuaTable>("loaded"),
+            package.get::<LuaFunction>("searchpath"),
+            package.get::<String>("path"),
+        ) else { return Vec::new() };
+        loaded.pairs::<String, LuaValue>().flatten().filter_map(|(name, _)| {
+            let found = search.call::<Option<String>>((name, path.as_str())).ok().flatten()?;
+            Path::new(&found).canonicalize().ok()
+        }).collect()
+    }
+
+
+    /// Seconds until the next F.every/F.after timer is due, if any.
+    pub fn next_timer(&self) -> Option<f64> {
+        let next: LuaFunction = self.lua.named_registry_value(NEXT_TIMER).ok()?;
+        next.call::<Option<f64>>(()).ok().flatten()
+    }
+
+    /// Call get_status() -> string or status table. Bots without one show
+    /// their F.fsm state.
+    pub fn get_status(&self) -> Result<Status> {
         let table: LuaTable = self.lua.registry_value(&self.bot_key).map_err(lua_err)?;
         match table.get::<LuaFunction>("get_status") {
             Ok(f) => {
-                let s: String = f.call(()).map_err(lua_err)?;
-                Ok(s)
+                let value: LuaValue = self.budgeted("get_status", || f.call(())).map_err(lua_err)?;
+                parse_status(value).map_err(lua_err)
+            }
+            Err(_) => {
+                let fsm: LuaFunction = self.lua.named_registry_value(FSM_STATUS).map_err(lua_err)?;
+                let s: Option<String> = fsm.call(()).map_err(lua_err)?;
+                Ok(Status::text(s.unwrap_or_default()))
             }
-            Err(_) => Ok(String::new()),
         }
     }
 
@@ -256,28 +899,120 @@ impl LuaBot {
     pub fn reset(&self) -> Result<()> {
         let table: LuaTable = self.lua.registry_value(&self.bot_key).map_err(lua_err)?;
         if let Ok(f) = table.get::<LuaFunction>("reset") {
-            f.call::<()>(())
-                .map_err(|e| { (self.on_error)(format_mlua_error(&e)); lua_err(e) })?;
+            self.budgeted("reset", || f.call::<()>(()))
+                .map_err(|e| self.fail(e))?;
         }
         Ok(())
     }
 
     /// Call stop(). Drops any suspended coroutine first.
+    /// F.store is flushed afterwards even if stop() fails.
     pub fn stop(&mut self) -> Result<()> {
         if let Some(key) = self.suspended.take() {
             self.lua.remove_registry_value(key).ok();
         }
+        let result = self.call_stop();
+        for s in &self.stores {
+            s.lock().unwrap().flush();
+        }
+        result
+    }
+
+    /// Errors in stop() are only logged: the instance is going away anyway,
+    /// so they must not count against the restart policy.
+    fn call_stop(&self) -> Result<()> {
         let table: LuaTable = self.lua.registry_value(&self.bot_key).map_err(lua_err)?;
         if let Ok(f) = table.get::<LuaFunction>("stop") {
-            f.call::<()>(())
-                .map_err(|e| { (self.on_error)(format_mlua_error(&e)); lua_err(e) })?;
+            self.budgeted_waits("stop", STOP_BUDGET, || f.call::<()>(()))
+                .map_err(|e| {
+                    let lines = format_mlua_error(&e);
+                    logger::error_p(&self.info.id, &format!("stop() failed:\n  {}", lines.join("\n  ")));
+                    lua_err(e)
+                })?;
         }
         Ok(())
     }
 
-    /// Activate the window (bring to foreground).
-    pub fn activate(&self) {
-        self.win.borrow_mut().activate();
+    /// Refresh the window and call on_window_closed(), on_resize(w, h) or
+    /// on_focus_lost() for what changed since the last check. Returns false
+    /// once the window is gone. Errors in on_resize() and on_focus_lost() go
+    /// through on_error like tick errors.
+    pub fn check_window(&mut self) -> Result<bool> {
+        let region = {
+            let mut win = self.win.borrow_mut();
+            win.update();
+            win.region()
+        };
+        let table: LuaTable = self.lua.registry_value(&self.bot_key).map_err(lua_err)?;
+        let Some(region) = region else {
+            self.focus_lost.set(false);
+            // The instance is going away, so like stop() this is only logged
+            if let Ok(f) = table.get::<LuaFunction>("on_window_closed") {
+                if let Err(e) = self.budgeted("on_window_closed", || f.call::<()>(())) {
+                    let lines = format_mlua_error(&e);
+                    logger::error_p(&self.info.id, &format!("on_window_closed() failed:\n  {}", lines.join("\n  ")));
+                }
+            }
+            return Ok(false);
+        };
+        let size = (region.w, region.h);
+        if self.size != Some(size) {
+            self.size = Some(size);
+            if let Ok(f) = table.get::<LuaFunction>("on_resize") {
+                self.budgeted("on_resize", || f.call::<()>(size))
+                    .map_err(|e| self.fail(e))?;
+            }
+        }
+        if self.focus_lost.replace(false) {
+            if let Ok(f) = table.get::<LuaFunction>("on_focus_lost") {
+                self.budgeted("on_focus_lost", || f.call::<()>(()))
+                    .map_err(|e| self.fail(e))?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Run a call into the VM under the tick budget.
+    fn budgeted<T>(&self, what: &'static str, f: impl FnOnce() -> T) -> T {
+        self.budget.arm(&self.lua, None, what);
+        let out = f();
+        self.budget.disarm();
+        out
+    }
+
+    /// Run a call into the VM with `limit` of wall time, waits included.
+    fn budgeted_waits<T>(&self, what: &'static str, limit: Duration, f: impl FnOnce() -> T) -> T {
+        let tick_limit = self.budget.limit.replace(limit);
+        self.budget.waits.set(true);
+        let out = self.budgeted(what, f);
+        self.budget.waits.set(false);
+        self.budget.limit.set(tick_limit);
+        out
+    }
+
+    /// Bring the window to the foreground, waiting `activation_delay` only
+    /// if focus actually moved. With `verify_activation`, also waits for the
+    /// window to report foreground and returns false if it never does.
+    pub fn activate(&self) -> bool {
+        let mut win = self.win.borrow_mut();
+        if win.activate() {
+            sleep::ms(self.activation.delay.as_millis() as u64);
+        }
+        if !self.activation.verify {
+            return true;
+        }
+        let deadline = Instant::now() + VERIFY_TIMEOUT;
+        while !win.is_foreground() {
+            if Instant::now() >= deadline || cancel::requested() {
+                return false;
+            }
+            sleep::ms(50);
+        }
+        true
+    }
+
+    pub fn bot_name(&self) -> &str {
+        &self.info.bot
     }
 
     /// Set whether the window is currently active (controls whether win actions are allowed).
@@ -286,21 +1021,239 @@ impl LuaBot {
     }
 }
 
+/// Blackboard table shared between VMs (`F.shared`). Reads return copies;
+/// assign a whole value to change it.
+struct LuaShared(bus::Blackboard);
+
+impl LuaUserData for LuaShared {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::Index, |lua, this, key: String| {
+            match this.0.lock().unwrap().get(&key) {
+                Some(v) => lua.to_value(v),
+                None => Ok(LuaNil),
+            }
+        });
+
+        methods.add_meta_method(LuaMetaMethod::NewIndex, |lua, this, (key, value): (String, LuaValue)| {
+            if value.is_nil() {
+                this.0.lock().unwrap().remove(&key);
+            } else {
+                let json = lua.from_value(value)?;
+                this.0.lock().unwrap().insert(key, json);
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Expose the instance identity as the F.instance table.
+fn register_instance(lua: &Lua, info: &InstanceInfo) -> mlua::Result<()> {
+    let instance = lua.create_table()?;
+    instance.set("bot", info.bot.as_str())?;
+    instance.set("id", info.id.as_str())?;
+    instance.set("index", info.index)?;
+    instance.set("count", info.count)?;
+    lua.globals().get::<LuaTable>("F")?.set("instance", instance)
+}
+
+/// Register F.publish/F.subscribe/F.shared (scoped to the bot) and the same
+/// trio under F.global (visible to every bot).
+fn register_bus(lua: &Lua, bot_name: &str, instance_id: &str) -> mlua::Result<()> {
+    let f_table: LuaTable = lua.globals().get("F")?;
+    let global = lua.create_table()?;
+
+    for (table, scope) in [(&f_table, bot_name), (&global, bus::GLOBAL)] {
+        let scope_name = scope.to_string();
+        let from = instance_id.to_string();
+        table.set("publish", lua.create_function(move |lua, (topic, value): (String, LuaValue)| {
+            bus::publish(Message {
+                scope: scope_name.clone(),
+                topic,
+                value: lua.from_value(value)?,
+                from: from.clone(),
+            });
+            Ok(())
+        })?)?;
+        table.set("shared", LuaShared(bus::shared(scope)))?;
+    }
+    f_table.set("global", global)?;
+
+    // Subscriptions live in Lua; the orchestrator asks has_subscriber() before
+    // queueing a message and dispatch() runs the handlers during the next tick.
+    let (has, dispatch): (LuaFunction, LuaFunction) = lua.load(r#"
+        local bot_scope, global_scope = ...
+        local subs = {}
+
+        local function key(scope, topic)
+            return scope .. "\0" .. topic
+        end
+
+        local function subscribe(scope)
+            return function(topic, fn)
+                local k = key(scope, topic)
+                subs[k] = subs[k] or {}
+                table.insert(subs[k], fn)
+            end
+        end
+
+        local function unsubscribe(scope)
+            return function(topic)
+                subs[key(scope, topic)] = nil
+            end
+        end
+
+        F.subscribe = subscribe(bot_scope)
+        F.unsubscribe = unsubscribe(bot_scope)
+        F.global.subscribe = subscribe(global_scope)
+        F.global.unsubscribe = unsubscribe(global_scope)
+
+        local function has(scope, topic)
+            return subs[key(scope, topic)] ~= nil
+        end
+
+        local function dispatch(scope, topic, value, from)
+            for _, fn in ipairs(subs[key(scope, topic)] or {}) do
+                fn(value, from)
+            end
+        end
+
+        return has, dispatch
+    "#).call((bot_name, bus::GLOBAL))?;
+    lua.set_named_registry_value(HAS_SUBSCRIBER, has)?;
+    lua.set_named_registry_value(DISPATCH, dispatch)?;
+
+    Ok(())
+}
+
+/// Convert a get_status() result: a string, nil, or
+/// `{ text=, progress=0..1, fields={k=v}, level="ok|warn|error" }`.
+fn parse_status(value: LuaValue) -> mlua::Result<Status> {
+    let table = match value {
+        LuaValue::Nil => return Ok(Status::default()),
+        LuaValue::Table(t) => t,
+        other => return Ok(Status::text(other.to_string()?)),
+    };
+    let mut fields = Vec::new();
+    if let Some(t) = table.get::<Option<LuaTable>>("fields")? {
+        for pair in t.pairs::<String, LuaValue>() {
+            let (key, value) = pair?;
+            fields.push((key, value.to_string()?));
+        }
+    }
+    fields.sort();
+    Ok(Status {
+        text: table.get::<Option<String>>("text")?.unwrap_or_default(),
+        progress: table.get::<Option<f64>>("progress")?
+            .filter(|p| p.is_finite())
+            .map(|p| p.clamp(0.0, 1.0)),
+        fields,
+        level: table.get::<Option<String>>("level")?.as_deref().and_then(StatusLevel::parse),
+    })
+}
+
+/// Parse the `actions` table: `name = { label = "...", fn = function() end }`.
+/// Sorted by name since Lua tables have no order.
+fn parse_actions(table: LuaTable) -> Result<Vec<BotAction>> {
+    let mut actions = Vec::new();
+    for pair in table.pairs::<String, LuaTable>() {
+        let (name, action) = pair.map_err(|e| anyhow!("actions: {}", e))?;
+        action.get::<LuaFunction>("fn")
+            .map_err(|_| anyhow!("action \"{}\" has no fn", name))?;
+        let label = action.get::<Option<String>>("label").map_err(lua_err)?
+            .unwrap_or_else(|| name.clone());
+        actions.push(BotAction { name, label });
+    }
+    actions.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(actions)
+}
+
+/// Parse the `options` schema array from a bot table.
+fn parse_options(lua: &Lua, list: LuaTable) -> Result<Vec<OptionSpec>> {
+    let mut specs = Vec::new();
+    for item in list.sequence_values::<LuaTable>() {
+        let item = item.map_err(lua_err)?;
+        let name: String = item.get("name")
+            .map_err(|_| anyhow!("options: every entry needs a string `name`"))?;
+        let default: Value = lua.from_value(item.get("default").map_err(lua_err)?)
+            .map_err(|e| anyhow!("options.{}: bad default: {}", name, e))?;
+        let kind = match item.get::<Option<String>>("type").map_err(lua_err)? {
+            Some(t) => OptionType::parse(&t)
+                .ok_or_else(|| anyhow!("options.{}: unknown type `{}`", name, t))?,
+            None => OptionType::of(&default)
+                .ok_or_else(|| anyhow!("options.{}: needs a `type` or a `default`", name))?,
+        };
+        let spec = OptionSpec {
+            kind,
+            default,
+            min: item.get("min").map_err(lua_err)?,
+            max: item.get("max").map_err(lua_err)?,
+            description: item.get::<Option<String>>("description").map_err(lua_err)?
+                .unwrap_or_default(),
+            name,
+        };
+        spec.validate(&spec.default).map_err(|e| anyhow!("options.{}", e))?;
+        specs.push(spec);
+    }
+    Ok(specs)
+}
+
+/// Build a get/set/delete table over a shared store.
+/// Values round-trip through JSON, so only plain data can be stored.
+fn store_table(lua: &Lua, store: &Arc<Mutex<Store>>) -> mlua::Result<LuaTable> {
+    let table = lua.create_table()?;
+
+    // get(key, default?) -> value or default
+    let s = Arc::clone(store);
+    table.set("get", lua.create_function(move |lua, (key, default): (String, LuaValue)| {
+        match s.lock().unwrap().get(&key) {
+            Some(v) => lua.to_value(v),
+            None => Ok(default),
+        }
+    })?)?;
+
+    // set(key, value) — setting nil deletes the key
+    let s = Arc::clone(store);
+    table.set("set", lua.create_function(move |lua, (key, value): (String, LuaValue)| {
+        if value.is_nil() {
+            s.lock().unwrap().delete(&key);
+        } else {
+            let json = lua.from_value(value)?;
+            s.lock().unwrap().set(&key, json);
+        }
+        Ok(())
+    })?)?;
+
+    let s = Arc::clone(store);
+    table.set("delete", lua.create_function(move |_, key: String| {
+        s.lock().unwrap().delete(&key);
+        Ok(())
+    })?)?;
+
+    Ok(table)
+}
+
 /// Register the F.* global table into a Lua state.
-fn register_globals(lua: &Lua, tag: &str) -> mlua::Result<()> {
+fn register_globals(lua: &Lua, tag: &str, budget: &Rc<Budget>) -> mlua::Result<()> {
     let f_table = lua.create_table()?;
 
     // F.sleep(secs, p?) is defined as a Lua function below (after F is set globally)
     // so it can yield across coroutines. Rust closures cannot yield in mlua.
 
     // F.delay(ms, p?) — blocking sleep, holds the thread (for short pauses between actions)
-    let delay_fn = lua.create_function(|_, (ms, percent): (f64, Option<f64>)| {
+    let budget = Rc::clone(budget);
+    let delay_fn = lua.create_function(move |_, (ms, percent): (f64, Option<f64>)| {
         let ms = ms.max(0.0).round() as u64;
-        match percent {
-            Some(pct) => sleep::jittered_ms(ms, pct),
-            None => sleep::ms(ms),
+        let left = budget.wait_left();
+        budget.exclude(|| match (percent, left) {
+            (_, Some(left)) if left < ms => sleep::ms(left),
+            (Some(pct), _) => sleep::jittered_ms(ms, pct),
+            (None, _) => sleep::ms(ms),
+        });
+        check_cancel()?;
+        match left {
+            Some(left) if left < ms => Err(budget.exceeded()),
+            _ => Ok(()),
         }
-        Ok(())
     })?;
     f_table.set("delay", delay_fn)?;
 
@@ -326,6 +1279,9 @@ fn register_globals(lua: &Lua, tag: &str) -> mlua::Result<()> {
     })?;
     f_table.set("log", log_fn)?;
 
+    // F.now() — monotonic seconds, shared clock across all bots
+    f_table.set("now", lua.create_function(|_, ()| Ok(sleep::now()))?)?;
+
     lua.globals().set("F", f_table)?;
 
     // F.sleep(secs, p?) — defined as Lua so it can coroutine.yield
@@ -339,5 +1295,212 @@ fn register_globals(lua: &Lua, tag: &str) -> mlua::Result<()> {
         end
     "#).exec()?;
 
+    // F.every / F.after / F.cancel — named timers fired by the runtime before
+    // each tick. The fire/next hooks are kept in the registry, out of the bots' reach.
+    let (fire, next): (LuaFunction, LuaFunction) = lua.load(r#"
+        local timers = {}
+
+        F.every = function(name, secs, fn)
+            assert(secs > 0, "F.every: interval must be positive")
+            timers[name] = { due = F.now() + secs, every = secs, fn = fn }
+        end
+
+        F.after = function(name, secs, fn)
+            timers[name] = { due = F.now() + secs, fn = fn }
+        end
+
+        F.cancel = function(name)
+            timers[name] = nil
+        end
+
+        local function fire()
+            local now = F.now()
+            local due = {}
+            for name, t in pairs(timers) do
+                if t.due <= now then due[#due + 1] = name end
+            end
+            table.sort(due, function(a, b) return timers[a].due < timers[b].due end)
+            for _, name in ipairs(due) do
+                -- an earlier callback may have cancelled or replaced this timer
+                local t = timers[name]
+                if t and t.due <= now then
+                    if t.every then
+                        t.due = t.due + t.every
+                        if t.due <= now then t.due = now + t.every end
+                    else
+                        timers[name] = nil
+                    end
+                    t.fn()
+                end
+            end
+        end
+
+        local function next_due()
+            local min
+            for _, t in pairs(timers) do
+                if not min or t.due < min then min = t.due end
+            end
+            return min and math.max(0, min - F.now())
+        end
+
+        return fire, next_due
+    "#).eval()?;
+    lua.set_named_registry_value(FIRE_TIMERS, fire)?;
+    lua.set_named_registry_value(NEXT_TIMER, next)?;
+
+    // F.fsm{ initial=, states={ NAME={ enter=, tick=, exit= } } } — state machine
+    // helper. The status hook feeds get_status() for bots that don't define one.
+    let fsm_status: LuaFunction = lua.load(r#"
+        local function fmt_secs(s)
+            s = math.floor(s)
+            if s < 60 then return s .. "s" end
+            if s < 3600 then return string.format("%dm%02ds", s // 60, s % 60) end
+            return string.format("%dh%02dm", s // 3600, s % 3600 // 60)
+        end
+
+        local Fsm = {}
+        Fsm.__index = Fsm
+
+        -- Switch state: exit(m) on the old one, log, enter(m, ...) on the new one
+        function Fsm:go(name, ...)
+            local def = self.states[name]
+            if not def then error("F.fsm: unknown state " .. tostring(name), 2) end
+            local old = self.states[self.state]
+            if self.entered and old.exit then old.exit(self) end
+            F.log((self.name and self.name .. ": " or "") .. self.state .. " -> " .. name)
+            self.state = name
+            self.since = F.now()
+            self.entered = true
+            if def.enter then def.enter(self, ...) end
+        end
+
+        -- Run the current state's tick(m, ...). Returning a state name transitions
+        -- to it; the value after it (or a plain number) is passed through as cooldown.
+        function Fsm:tick(...)
+            if not self.entered then
+                self.entered = true
+                local def = self.states[self.state]
+                if def.enter then def.enter(self) end
+            end
+            local def = self.states[self.state]
+            if not def.tick then return nil end
+            local r, cd = def.tick(self, ...)
+            if type(r) == "string" then
+                self:go(r)
+                return cd
+            end
+            return r
+        end
+
+        -- Seconds spent in the current state
+        function Fsm:elapsed()
+            return F.now() - self.since
+        end
+
+        function Fsm:status()
+            return self.state .. " " .. fmt_secs(self:elapsed())
+        end
+
+        -- Back to the initial state without calling exit()/logging
+        function Fsm:reset()
+            self.state = self.initial
+            self.since = F.now()
+            self.entered = false
+        end
+
+        local last
+        F.fsm = function(def)
+            assert(def.states and def.states[def.initial], "F.fsm: initial must name a state")
+            local m = setmetatable({
+                name = def.name,
+                initial = def.initial,
+                states = def.states,
+            }, Fsm)
+            m:reset()
+            last = m
+            return m
+        end
+
+        return function()
+            return last and last:status()
+        end
+    "#).eval()?;
+    lua.set_named_registry_value(FSM_STATUS, fsm_status)?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::Platform;
+    use crate::platform::stub::StubPlatform;
+
+    /// Write `code` as the main.lua of a bot called `name` in a fresh folder.
+    fn script(name: &str, code: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("finger-lua-{}-{}", std::process::id(), name));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.lua");
+        std::fs::write(&path, code).unwrap();
+        path
+    }
+
+    fn load(name: &str, path: &Path) -> LuaBot {
+        let inst = Instance::new(name, 1, 7, "Game".into());
+        LuaBot::new(path, inst.info(name, 1), StubPlatform.create_window("x", 7), Map::new(), Arc::new(|_| {}))
+            .unwrap()
+    }
+
+    #[test]
+    fn stop_budget_counts_waits() {
+        let path = script("stop-budget", r#"
+            return {
+                window_pattern = "x", description = "",
+                tick = function() return 1 end,
+                stop = function() F.delay(10000) end,
+            }
+        "#);
+        let mut bot = load("stop-budget", &path);
+        let start = Instant::now();
+        assert!(bot.stop().is_err());
+        let took = start.elapsed();
+        assert!(took + Duration::from_millis(100) >= STOP_BUDGET && took < STOP_BUDGET + Duration::from_secs(1), "{:?}", took);
+    }
+
+    #[test]
+    fn reload_stops_the_old_vm() {
+        let code = |version: u32| format!(r#"
+            local kept
+            return {{
+                window_pattern = "x", description = "",
+                start = function(w, opts, snap) kept = snap end,
+                tick = function() return 1 end,
+                snapshot = function() return {{ from = {v} }} end,
+                stop = function() F.store.set("stopped", {{ v = {v}, kept = kept }}) end,
+            }}
+        "#, v = version);
+        let path = script("reload", &code(1));
+        let mut bot = load("reload", &path);
+        std::fs::write(&path, code(2)).unwrap();
+        let win = StubPlatform.create_window("x", 7);
+        bot.reload(&path, win, Map::new()).unwrap();
+        let stopped = store::open("reload/store").lock().unwrap().get("stopped").cloned();
+        assert_eq!(stopped, Some(serde_json::json!({ "v": 1 })));
+        bot.stop().unwrap();
+        let stopped = store::open("reload/store").lock().unwrap().get("stopped").cloned();
+        assert_eq!(stopped, Some(serde_json::json!({ "v": 2, "kept": { "from": 1 } })));
+    }
+
+    #[test]
+    fn stop_may_wait_within_its_budget() {
+        let path = script("stop-wait", r#"
+            return {
+                window_pattern = "x", description = "",
+                tick = function() return 1 end,
+                stop = function() F.delay(50) end,
+            }
+        "#);
+        assert!(load("stop-wait", &path).stop().is_ok());
+    }
+}
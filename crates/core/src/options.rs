@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Value type of a bot option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionType {
+    Number,
+    Integer,
+    String,
+    Boolean,
+    /// Arbitrary Lua table, edited as JSON
+    Table,
+}
+
+impl OptionType {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "number" => Some(Self::Number),
+            "integer" => Some(Self::Integer),
+            "string" => Some(Self::String),
+            "boolean" | "bool" => Some(Self::Boolean),
+            "table" => Some(Self::Table),
+            _ => None,
+        }
+    }
+
+    /// Infer the type from a default value.
+    pub fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(_) => Some(Self::Boolean),
+            Value::Number(n) if n.is_i64() || n.is_u64() => Some(Self::Integer),
+            Value::Number(_) => Some(Self::Number),
+            Value::String(_) => Some(Self::String),
+            Value::Array(_) | Value::Object(_) => Some(Self::Table),
+            Value::Null => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Number => "number",
+            Self::Integer => "integer",
+            Self::String => "string",
+            Self::Boolean => "boolean",
+            Self::Table => "table",
+        }
+    }
+}
+
+/// One entry of a bot's `options` schema.
+#[derive(Debug, Clone)]
+pub struct OptionSpec {
+    pub name: String,
+    pub kind: OptionType,
+    pub default: Value,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub description: String,
+}
+
+/// Where an option's effective value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionSource {
+    Default,
+    Bot,
+    Instance,
+}
+
+/// User overrides for one bot: bot-wide values and per-instance values
+/// keyed by `Instance::key`. Persisted in `Settings`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptionValues {
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub bot: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub instances: BTreeMap<String, Map<String, Value>>,
+}
+
+impl OptionSpec {
+    /// Check a value against the type and range. `null` is always accepted.
+    pub fn validate(&self, value: &Value) -> Result<(), String> {
+        let ok = match (self.kind, value) {
+            (_, Value::Null) => return Ok(()),
+            (OptionType::Number, Value::Number(_)) => true,
+            (OptionType::Integer, Value::Number(n)) => {
+                n.is_i64() || n.as_f64().is_some_and(|f| f.fract() == 0.0)
+            }
+            (OptionType::String, Value::String(_)) => true,
+            (OptionType::Boolean, Value::Bool(_)) => true,
+            (OptionType::Table, Value::Array(_) | Value::Object(_)) => true,
+            _ => false,
+        };
+        if !ok {
+            return Err(format!("{}: expected {}", self.name, self.kind.name()));
+        }
+        if let Some(n) = value.as_f64() {
+            if self.min.is_some_and(|min| n < min) || self.max.is_some_and(|max| n > max) {
+                return Err(format!(
+                    "{}: {} out of range {}..{}",
+                    self.name, n,
+                    self.min.map_or(String::new(), |v| v.to_string()),
+                    self.max.map_or(String::new(), |v| v.to_string()),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse user-entered text into a value of this option's type.
+    pub fn parse(&self, text: &str) -> Result<Value, String> {
+        let text = text.trim();
+        let value = match self.kind {
+            OptionType::Number => text.parse::<f64>().ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| format!("{}: not a number", self.name))?,
+            OptionType::Integer => text.parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| format!("{}: not an integer", self.name))?,
+            OptionType::String => Value::String(text.to_string()),
+            OptionType::Boolean => match text.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Value::Bool(true),
+                "false" | "no" | "off" | "0" => Value::Bool(false),
+                _ => return Err(format!("{}: expected true or false", self.name)),
+            },
+            OptionType::Table => serde_json::from_str(text)
+                .map_err(|e| format!("{}: invalid JSON: {}", self.name, e))?,
+        };
+        self.validate(&value)?;
+        Ok(value)
+    }
+}
+
+/// Render a value for display and editing (strings unquoted, tables as JSON).
+pub fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "nil".into(),
+        v => v.to_string(),
+    }
+}
+
+impl OptionValues {
+    /// Effective value of `spec` for an instance (or bot-wide when `inst_key` is None):
+    /// instance override, then bot override, then the schema default.
+    /// Overrides that no longer pass validation are skipped.
+    pub fn effective<'a>(&'a self, spec: &'a OptionSpec, inst_key: Option<&str>) -> (&'a Value, OptionSource) {
+        let valid = |v: &&Value| spec.validate(v).is_ok();
+        if let Some(v) = inst_key
+            .and_then(|k| self.instances.get(k))
+            .and_then(|m| m.get(&spec.name))
+            .filter(valid)
+        {
+            return (v, OptionSource::Instance);
+        }
+        if let Some(v) = self.bot.get(&spec.name).filter(valid) {
+            return (v, OptionSource::Bot);
+        }
+        (&spec.default, OptionSource::Default)
+    }
+
+    /// Resolve every option in the schema for one instance.
+    pub fn resolve(&self, specs: &[OptionSpec], inst_key: &str) -> Map<String, Value> {
+        specs.iter()
+            .map(|spec| (spec.name.clone(), self.effective(spec, Some(inst_key)).0.clone()))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bot.is_empty() && self.instances.values().all(|m| m.is_empty())
+    }
+}
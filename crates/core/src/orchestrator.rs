@@ -3,16 +3,59 @@ use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, mpsc};
 use std::time::{Duration, Instant};
 
+use chrono::Local;
+
 use crate::types::*;
+use crate::bus;
+use crate::cancel;
 use crate::platform::Platform;
-use crate::lua_rt::LuaBot;
+use crate::lua_rt::{self, LuaBot};
+use crate::worker::{self, Event, Outcome, Worker};
+use crate::scheduler::Scheduler;
+use crate::options::OptionValues;
+use crate::schedule::Usage;
 use crate::logger;
+use crate::store;
+use crate::watcher::BotWatcher;
+
+/// First delay before recreating a failed instance; doubles per retry
+const BACKOFF_BASE: Duration = Duration::from_secs(2);
+const BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+/// Longest the loop sleeps with nothing due, so hot reload keeps polling
+const IDLE_WAIT: Duration = Duration::from_secs(1);
+
+/// Retry delay after verify_activation found the window still behind
+const FOCUS_RETRY: Duration = Duration::from_secs(2);
+
+/// How often the windows of running instances are checked for closing,
+/// resizing and lost focus between their own ticks
+const WINDOW_CHECK: Duration = Duration::from_secs(2);
+
+/// An instance that ran this long since its last error starts over with
+/// a fresh retry budget
+const RETRY_RESET: Duration = Duration::from_secs(600);
+
+/// Runtime errors reported by on_error, handled by the orchestrator loop
+type Failures = Arc<Mutex<Vec<(String, Vec<String>)>>>;
+
+/// Where instance threads report to: on_error queues failures, the rest
+/// comes back as events
+struct Reports {
+    failures: Failures,
+    events: mpsc::Sender<Event>,
+}
+
+/// Today's usage of each bot with a daily quota, by bot name
+type Usages = HashMap<String, Usage>;
 
 /// Build the on_error callback for a bot instance.
-/// The VM fires this with formatted traceback lines; we log and disable the entry.
+/// The VM fires this with formatted traceback lines; we log and queue the
+/// failure for `handle_failures`. It must not lock the bot state: it can fire
+/// from inside create_bot while the caller holds that lock.
 fn make_on_error(
     id: String,
-    state: Arc<Mutex<Vec<BotEntry>>>,
+    failures: Failures,
 ) -> Arc<dyn Fn(Vec<String>) + Send + Sync> {
     Arc::new(move |lines: Vec<String>| {
         // First line is the header; the rest are indented continuation lines.
@@ -25,20 +68,189 @@ fn make_on_error(
             msg.push_str(line);
         }
         logger::error_p(&id, &msg);
-        let mut entries = state.lock().unwrap();
-        if let Some(entry) = entries.iter_mut()
-            .find(|e| e.instances.iter().any(|i| i.id == id))
-        {
+        failures.lock().unwrap().push((id.clone(), lines));
+    })
+}
+
+/// Tear down instances that failed and apply their bot's restart policy:
+/// schedule a restart with exponential backoff, or disable the bot once the
+/// retry budget is spent.
+fn handle_failures(
+    failures: &Failures,
+    state: &Arc<Mutex<Vec<BotEntry>>>,
+    bots: &mut HashMap<String, Worker>,
+    scheduler: &mut Scheduler,
+    restarts: &mut HashMap<String, Instant>,
+) {
+    let failed = std::mem::take(&mut *failures.lock().unwrap());
+    if failed.is_empty() {
+        return;
+    }
+    let mut entries = state.lock().unwrap();
+    for (id, lines) in failed {
+        if let Some(b) = bots.remove(&id) { b.stop(); }
+        scheduler.remove(&id);
+
+        let Some(entry) = entries.iter_mut()
+            .find(|e| e.instances.iter().any(|i| i.id == id)) else { continue };
+        let restart = entry.restart;
+        let enabled = entry.enabled;
+        let Some(inst) = entry.instances.iter_mut().find(|i| i.id == id) else { continue };
+        inst.error = lines.into_iter().next();
+        inst.status = Status::default();
+        inst.stuck = None;
+        inst.last_error_at = Some(Instant::now());
+        if !enabled || !inst.enabled {
+            continue;
+        }
+
+        if restart.allows(inst.retries) {
+            inst.retries += 1;
+            let delay = BACKOFF_BASE.saturating_mul(1 << (inst.retries - 1).min(16)).min(BACKOFF_MAX);
+            logger::warn(&format!(
+                "restarting {} in {}s (attempt {})", id, delay.as_secs(), inst.retries
+            ));
+            restarts.insert(id, Instant::now() + delay);
+        } else {
+            logger::error(&format!("{} out of restarts, disabling {}", id, entry.name));
             entry.enabled = false;
-            if let Some(inst) = entry.instances.iter_mut().find(|i| i.id == id) {
-                inst.error = lines.into_iter().next();
-                inst.status = String::new();
+        }
+    }
+}
+
+/// Recreate failed instances whose backoff has elapsed.
+fn run_restarts(
+    state: &Arc<Mutex<Vec<BotEntry>>>,
+    platform: &dyn Platform,
+    reports: &Reports,
+    bots: &mut HashMap<String, Worker>,
+    restarts: &mut HashMap<String, Instant>,
+) {
+    let now = Instant::now();
+    let due: Vec<String> = restarts.iter()
+        .filter(|(_, at)| now >= **at)
+        .map(|(id, _)| id.clone())
+        .collect();
+    if due.is_empty() {
+        return;
+    }
+    let entries = state.lock().unwrap();
+    for id in due {
+        restarts.remove(&id);
+        let Some(entry) = entries.iter()
+            .find(|e| e.enabled && e.hold.is_none() && e.instances.iter().any(|i| i.id == id)) else { continue };
+        let Some(inst) = entry.instances.iter().find(|i| i.id == id && i.enabled) else { continue };
+        if bots.contains_key(&id) { continue; }
+        logger::info(&format!("restarting {}", id));
+        if let Some(bot) = create_bot(entry, inst, platform, reports) {
+            bots.insert(id, bot);
+        }
+    }
+}
+
+/// Drop instances whose window closed from their entries and update the
+/// instance count of their siblings; the sweep then stops their bots.
+fn remove_closed(
+    state: &Arc<Mutex<Vec<BotEntry>>>,
+    bots: &HashMap<String, Worker>,
+    closed: &[String],
+) {
+    if closed.is_empty() {
+        return;
+    }
+    let mut entries = state.lock().unwrap();
+    for entry in entries.iter_mut() {
+        let before = entry.instances.len();
+        entry.instances.retain(|i| !closed.contains(&i.id));
+        if entry.instances.len() == before {
+            continue;
+        }
+        let count = entry.instances.len();
+        for inst in &entry.instances {
+            if let Some(b) = bots.get(&inst.id) { b.set_instance_count(count); }
+        }
+    }
+    for id in closed {
+        logger::info_p(id, "window closed");
+    }
+}
+
+/// Flag instances whose current step has taken longer than their bot's
+/// `stuck_after`, and log once where their Lua code is, as soon as that is
+/// known. The flag clears when the step ends.
+fn watch_stuck(state: &Arc<Mutex<Vec<BotEntry>>>, bots: &mut HashMap<String, Worker>) {
+    let mut entries = state.lock().unwrap();
+    for entry in entries.iter_mut() {
+        let limit = entry.stuck_after;
+        for inst in entry.instances.iter_mut() {
+            let Some(bot) = bots.get_mut(&inst.id) else { continue };
+            let Some((since, step)) = bot.activity().filter(|(since, _)| since.elapsed() >= limit) else {
+                inst.stuck = None;
+                bot.stuck = false;
+                continue;
+            };
+            if inst.stuck != Some((since, step)) {
+                inst.stuck = Some((since, step));
+                bot.stuck = true;
+                bot.traced = false;
+                logger::warn_p(&inst.id, &format!(
+                    "stuck in {} for {}s", step, since.elapsed().as_secs()
+                ));
+            }
+            if bot.traced {
+                continue;
+            }
+            if step == "activate" {
+                logger::warn_p(&inst.id, "no Lua code running: the platform's activate has not returned");
+                bot.traced = true;
+                continue;
+            }
+            match bot.trace().stack() {
+                Some(stack) => {
+                    let mut msg = String::from("Lua stack:");
+                    for line in stack.lines() {
+                        msg.push_str("\n  ");
+                        msg.push_str(line);
+                    }
+                    logger::warn_p(&inst.id, &msg);
+                    bot.traced = true;
+                }
+                // Busy in Lua: the budget hook records the stack shortly
+                None => bot.trace().request(),
             }
         }
-    })
+    }
+}
+
+/// Start the thread for one instance and load its bot with the resolved
+/// options. Returns None on failure; on_error has already fired.
+fn create_bot(
+    entry: &BotEntry,
+    inst: &Instance,
+    platform: &dyn Platform,
+    reports: &Reports,
+) -> Option<Worker> {
+    Worker::spawn(
+        entry.script_path.clone(), inst.info(&entry.name, entry.instances.len()),
+        platform.create_window(&entry.window_pattern, inst.window_id),
+        entry.option_values.resolve(&entry.options, &inst.key),
+        make_on_error(inst.id.clone(), Arc::clone(&reports.failures)),
+        reports.events.clone(),
+    )
+}
+
+/// Hand F.publish messages to every instance; those subscribed report back
+/// with `Event::Woken` and are made due then.
+fn route_messages(bots: &HashMap<String, Worker>) {
+    for msg in bus::drain() {
+        for bot in bots.values() {
+            bot.deliver(&msg);
+        }
+    }
 }
 
 /// Recursively find all directories containing `main.lua` under `dir`.
+/// The shared library directory is skipped.
 pub fn find_bot_dirs(dir: &Path) -> Vec<PathBuf> {
     let mut results = Vec::new();
     let entries = match std::fs::read_dir(dir) {
@@ -49,7 +261,9 @@ pub fn find_bot_dirs(dir: &Path) -> Vec<PathBuf> {
         let path = entry.path();
         if path.is_dir() {
             let name = path.file_name().unwrap_or_default().to_string_lossy();
-            if !name.starts_with('.') && name != "node_modules" {
+            let is_lib = lua_rt::lib_dir()
+                .is_some_and(|lib| path.canonicalize().is_ok_and(|p| p == lib));
+            if !name.starts_with('.') && name != "node_modules" && !is_lib {
                 let main_lua = path.join("main.lua");
                 if main_lua.is_file() {
                     results.push(main_lua);
@@ -80,15 +294,23 @@ pub fn load_bots(bots_dir: &Path) -> Vec<BotEntry> {
     for path in files {
         let name = derive_bot_name(&path, bots_dir);
         match LuaBot::load_meta(&path) {
-            Ok((pattern, description)) => {
+            Ok(meta) => {
                 entries.push(BotEntry {
                     name,
-                    window_pattern: pattern,
-                    description,
+                    window_pattern: meta.window_pattern,
+                    description: meta.description,
                     enabled: false,
                     instances: Vec::new(),
                     error: None,
                     script_path: path,
+                    options: meta.options,
+                    option_values: OptionValues::default(),
+                    restart: meta.restart,
+                    actions: meta.actions,
+                    priority: meta.priority,
+                    stuck_after: meta.stuck_after,
+                    schedule: None,
+                    hold: None,
                 });
             }
             Err(e) => {
@@ -103,32 +325,96 @@ pub fn load_bots(bots_dir: &Path) -> Vec<BotEntry> {
 /// Scan for windows matching each bot's pattern, populate instances.
 pub fn scan_instances(entries: &mut Vec<BotEntry>, platform: &dyn Platform) {
     for entry in entries.iter_mut() {
-        let windows = platform.get_instances(&entry.window_pattern);
-        entry.instances.clear();
-        for (wid, title) in windows {
-            entry.instances.push(Instance::new(&entry.name, wid, title));
+        entry.instances = Instance::for_windows(&entry.name, platform.get_instances(&entry.window_pattern));
+    }
+}
+
+/// Re-read a bot's metadata after its files changed and hot-swap the VM of
+/// every running instance. Broken code is logged and the old VMs keep running.
+/// A new window pattern means other windows, so the instances are stopped
+/// and scanned again instead.
+fn reload_bot(
+    script_path: &Path,
+    state: &Arc<Mutex<Vec<BotEntry>>>,
+    orch_state: &Mutex<OrchestratorState>,
+    platform: &dyn Platform,
+    reports: &Reports,
+    bots: &mut HashMap<String, Worker>,
+) {
+    let mut entries = state.lock().unwrap();
+    let Some(entry) = entries.iter_mut().find(|e| e.script_path == script_path) else { return };
+
+    let old_pattern = match LuaBot::load_meta(&entry.script_path) {
+        Ok(meta) => {
+            entry.description = meta.description;
+            entry.options = meta.options;
+            entry.restart = meta.restart;
+            entry.actions = meta.actions;
+            entry.priority = meta.priority;
+            entry.stuck_after = meta.stuck_after;
+            std::mem::replace(&mut entry.window_pattern, meta.window_pattern)
+        }
+        Err(e) => {
+            logger::error(&format!("reload {} failed, keeping old code: {}", entry.name, e));
+            return;
+        }
+    };
+    logger::info(&format!("{} changed on disk, reloading", entry.name));
+
+    if entry.window_pattern != old_pattern {
+        logger::info(&format!(
+            "{} now matches \"{}\", rescanning windows", entry.name, entry.window_pattern
+        ));
+        // Switched-off slots stay off if a window fills them again
+        let off: Vec<String> = entry.instances.iter()
+            .filter(|i| !i.enabled)
+            .map(|i| i.key.clone())
+            .collect();
+        for inst in &entry.instances {
+            if let Some(b) = bots.remove(&inst.id) { b.stop(); }
         }
+        entry.instances = Instance::for_windows(&entry.name, platform.get_instances(&entry.window_pattern));
+        for inst in entry.instances.iter_mut() {
+            inst.enabled = !off.contains(&inst.key);
+        }
+        let is_running = *orch_state.lock().unwrap() == OrchestratorState::Running;
+        if is_running && entry.enabled && entry.hold.is_none() {
+            for inst in entry.instances.iter().filter(|i| i.enabled) {
+                if let Some(bot) = create_bot(entry, inst, platform, reports) {
+                    bots.insert(inst.id.clone(), bot);
+                }
+            }
+        }
+        return;
+    }
+
+    for inst in &entry.instances {
+        let Some(bot) = bots.get(&inst.id) else { continue };
+        bot.reload(
+            &entry.script_path,
+            platform.create_window(&entry.window_pattern, inst.window_id),
+            entry.option_values.resolve(&entry.options, &inst.key),
+        );
     }
 }
 
-/// Drain pending commands. Returns false on Quit.
+/// Handle commands until `cmds` runs dry. Returns false on Quit.
 fn process_commands(
-    cmd_rx: &mpsc::Receiver<Command>,
+    cmds: impl Iterator<Item = Command>,
     state: &Arc<Mutex<Vec<BotEntry>>>,
     orch_state: &Mutex<OrchestratorState>,
     platform: &dyn Platform,
-    bots: &mut HashMap<String, LuaBot>,
-    cooldowns: &mut HashMap<String, Instant>,
+    reports: &Reports,
+    bots: &mut HashMap<String, Worker>,
+    scheduler: &mut Scheduler,
 ) -> bool {
-    while let Ok(cmd) = cmd_rx.try_recv() {
+    for cmd in cmds {
         match cmd {
             Command::Quit => {
                 logger::info("shutting down");
-                // Stop all bots
-                for (_, mut bot) in bots.drain() {
-                    bot.stop().ok();
-                }
-                cooldowns.clear();
+                worker::stop_all(bots.drain().map(|(_, bot)| bot));
+                store::flush_all();
+                scheduler.clear();
                 *orch_state.lock().unwrap() = OrchestratorState::Stopped;
                 return false;
             }
@@ -137,20 +423,34 @@ fn process_commands(
 
                 // Rescan windows, remove dead, add new
                 for entry in entries.iter_mut() {
-                    let wins = platform.get_instances(&entry.window_pattern);
+                    let mut wins = platform.get_instances(&entry.window_pattern);
+                    // New windows take free slots in window id order, like a scan
+                    wins.sort_by_key(|(wid, _)| *wid);
+                    // A window that comes back into a switched-off slot stays off
+                    let off: Vec<String> = entry.instances.iter()
+                        .filter(|i| !i.enabled)
+                        .map(|i| i.key.clone())
+                        .collect();
                     entry.instances.retain(|i| {
                         let alive = wins.iter().any(|(w, _)| *w == i.window_id);
                         if !alive {
-                            if let Some(mut b) = bots.remove(&i.id) { b.stop().ok(); }
-                            cooldowns.remove(&i.id);
+                            if let Some(b) = bots.remove(&i.id) { b.stop(); }
+                            scheduler.remove(&i.id);
                         }
                         alive
                     });
                     for (wid, title) in &wins {
                         if !entry.instances.iter().any(|i| i.window_id == *wid) {
-                            entry.instances.push(Instance::new(&entry.name, *wid, title.clone()));
+                            let slot = Instance::free_slot(&entry.instances);
+                            let mut inst = Instance::new(&entry.name, slot, *wid, title.clone());
+                            inst.enabled = !off.contains(&inst.key);
+                            entry.instances.push(inst);
                         }
                     }
+                    let count = entry.instances.len();
+                    for inst in &entry.instances {
+                        if let Some(b) = bots.get(&inst.id) { b.set_instance_count(count); }
+                    }
                 }
 
                 let Some(entry) = entries.get_mut(idx) else { continue };
@@ -158,26 +458,47 @@ fn process_commands(
 
                 let is_running = *orch_state.lock().unwrap() == OrchestratorState::Running;
 
-                if entry.enabled && is_running {
-                    for inst in &entry.instances {
+                if entry.enabled {
+                    // Turning a bot back on grants a fresh retry budget
+                    for inst in entry.instances.iter_mut() { inst.retries = 0; }
+                }
+
+                if entry.enabled && is_running && entry.hold.is_none() {
+                    for inst in entry.instances.iter().filter(|i| i.enabled) {
                         if bots.contains_key(&inst.id) {
-                            bots.get(&inst.id).unwrap().reset().ok();
+                            bots.get(&inst.id).unwrap().reset();
                         } else {
-                            match LuaBot::new(
-                                &entry.script_path, &inst.id,
-                                platform.create_window(&entry.window_pattern, inst.window_id),
-                                make_on_error(inst.id.clone(), Arc::clone(&state)),
-                            ) {
-                                Ok(bot) => { bots.insert(inst.id.clone(), bot); }
-                                Err(_) => {} // on_error already fired inside lua_rt
+                            if let Some(bot) = create_bot(entry, inst, platform, reports) {
+                                bots.insert(inst.id.clone(), bot);
                             }
                         }
                     }
                 } else if !entry.enabled {
                     // Stop bots for disabled entry
                     for inst in &entry.instances {
-                        if let Some(mut b) = bots.remove(&inst.id) { b.stop().ok(); }
-                        cooldowns.remove(&inst.id);
+                        if let Some(b) = bots.remove(&inst.id) { b.stop(); }
+                        scheduler.remove(&inst.id);
+                    }
+                }
+            }
+            Command::ToggleInstance { bot, key } => {
+                let mut entries = state.lock().unwrap();
+                let Some(entry) = entries.get_mut(bot) else { continue };
+                let Some(n) = entry.instances.iter().position(|i| i.key == key) else { continue };
+                let inst = &mut entry.instances[n];
+                logger::info(&format!("enable {}: {}", inst.id, inst.enabled));
+                if !inst.enabled {
+                    if let Some(b) = bots.remove(&inst.id) { b.stop(); }
+                    scheduler.remove(&inst.id);
+                    continue;
+                }
+                // Like the bot toggle, switching on grants a fresh retry budget
+                inst.retries = 0;
+                let is_running = *orch_state.lock().unwrap() == OrchestratorState::Running;
+                let inst = &entry.instances[n];
+                if is_running && entry.enabled && entry.hold.is_none() && !bots.contains_key(&inst.id) {
+                    if let Some(b) = create_bot(entry, inst, platform, reports) {
+                        bots.insert(inst.id.clone(), b);
                     }
                 }
             }
@@ -191,19 +512,15 @@ fn process_commands(
                     OrchestratorState::Running => {
                         // TUI set Running (was Stopped → start)
                         logger::info("orchestrator started");
+                        cancel::clear();
                         // Create bots for all enabled entries
                         let entries = state.lock().unwrap();
                         for entry in entries.iter() {
-                            if !entry.enabled { continue; }
-                            for inst in &entry.instances {
+                            if !entry.enabled || entry.hold.is_some() { continue; }
+                            for inst in entry.instances.iter().filter(|i| i.enabled) {
                                 if !bots.contains_key(&inst.id) {
-                                    match LuaBot::new(
-                                        &entry.script_path, &inst.id,
-                                        platform.create_window(&entry.window_pattern, inst.window_id),
-                                        make_on_error(inst.id.clone(), Arc::clone(&state)),
-                                    ) {
-                                        Ok(bot) => { bots.insert(inst.id.clone(), bot); }
-                                        Err(_) => {} // on_error already fired inside lua_rt
+                                    if let Some(bot) = create_bot(entry, inst, platform, reports) {
+                                        bots.insert(inst.id.clone(), bot);
                                     }
                                 }
                             }
@@ -212,26 +529,55 @@ fn process_commands(
                     OrchestratorState::Stopped => {}
                 }
             }
+            Command::Action { bot, instance, action } => {
+                let entries = state.lock().unwrap();
+                let Some(entry) = entries.get(bot) else { continue };
+                let mut queued = false;
+                for inst in &entry.instances {
+                    if instance.as_ref().is_some_and(|id| *id != inst.id) { continue; }
+                    // The instance reports back with Event::Woken to run it now
+                    if let Some(b) = bots.get(&inst.id) {
+                        b.queue_action(&action);
+                        queued = true;
+                    }
+                }
+                if !queued {
+                    logger::warn(&format!("action {}: {} is not running", action, entry.name));
+                }
+            }
+            Command::Abandon { bot, key } => {
+                let entries = state.lock().unwrap();
+                let Some(inst) = entries.get(bot)
+                    .and_then(|e| e.instances.iter().find(|i| i.key == key)) else { continue };
+                if bots.get(&inst.id).is_none_or(|b| b.activity().is_none()) {
+                    logger::warn(&format!("abandon {}: not in the middle of a run", inst.id));
+                    continue;
+                }
+                let Some(b) = bots.remove(&inst.id) else { continue };
+                scheduler.remove(&inst.id);
+                let step = b.activity().map_or("a run", |(_, step)| step);
+                let line = format!("abandoned while stuck in {}", step);
+                logger::error_p(&inst.id, &line);
+                b.abandon();
+                // Fails like any error, so the restart policy applies
+                reports.failures.lock().unwrap().push((inst.id.clone(), vec![line]));
+            }
             Command::Restart(_) => {
                 let is_running = *orch_state.lock().unwrap() == OrchestratorState::Running;
                 if !is_running { continue; }
 
                 logger::info("restarting all bots");
-                let entries = state.lock().unwrap();
-                for entry in entries.iter() {
-                    if !entry.enabled { continue; }
-                    for inst in &entry.instances {
-                        if let Some(mut b) = bots.remove(&inst.id) {
-                            b.stop().ok();
+                let mut entries = state.lock().unwrap();
+                for entry in entries.iter_mut() {
+                    if !entry.enabled || entry.hold.is_some() { continue; }
+                    for inst in entry.instances.iter_mut() { inst.retries = 0; }
+                    for inst in entry.instances.iter().filter(|i| i.enabled) {
+                        if let Some(b) = bots.remove(&inst.id) {
+                            b.stop();
                         }
-                        cooldowns.remove(&inst.id);
-                        match LuaBot::new(
-                            &entry.script_path, &inst.id,
-                            platform.create_window(&entry.window_pattern, inst.window_id),
-                            make_on_error(inst.id.clone(), Arc::clone(&state)),
-                        ) {
-                            Ok(bot) => { bots.insert(inst.id.clone(), bot); }
-                            Err(_) => {} // on_error already fired inside lua_rt
+                        scheduler.remove(&inst.id);
+                        if let Some(bot) = create_bot(entry, inst, platform, reports) {
+                            bots.insert(inst.id.clone(), bot);
                         }
                     }
                 }
@@ -241,7 +587,202 @@ fn process_commands(
     true
 }
 
-/// Main orchestration loop. Runs on a background thread.
+/// Re-check every bot's schedule. Counts the time since the last pass
+/// towards the daily quota of bots that were running, sets `hold` on bots
+/// that must not run now (the sweep at the end of the pass stops them with
+/// a clean stop()) and, while running, starts the instances of bots whose
+/// schedule just opened.
+fn apply_schedules(
+    state: &Arc<Mutex<Vec<BotEntry>>>,
+    platform: &dyn Platform,
+    reports: &Reports,
+    bots: &mut HashMap<String, Worker>,
+    usages: &mut Usages,
+    elapsed: Duration,
+    running: bool,
+) {
+    let now = Local::now().naive_local();
+    let mut entries = state.lock().unwrap();
+    for entry in entries.iter_mut() {
+        let Some(schedule) = &entry.schedule else { continue };
+        let usage = usages.entry(entry.name.clone()).or_insert_with(|| Usage::open(&entry.name));
+        if schedule.has_quota() && entry.instances.iter().any(|i| bots.contains_key(&i.id)) {
+            usage.add(now.date(), elapsed, 0);
+        }
+        let hold = schedule.hold(now, usage);
+        if hold == entry.hold {
+            continue;
+        }
+        if entry.enabled {
+            match &hold {
+                Some(h) => logger::info(&format!("{}: {}", entry.name, h.describe())),
+                None => logger::info(&format!("{}: schedule open", entry.name)),
+            }
+        }
+        let opened = entry.hold.is_some() && hold.is_none();
+        entry.hold = hold;
+        if opened && running && entry.enabled {
+            for inst in entry.instances.iter().filter(|i| i.enabled) {
+                if !bots.contains_key(&inst.id) {
+                    if let Some(bot) = create_bot(entry, inst, platform, reports) {
+                        bots.insert(inst.id.clone(), bot);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Queue instances that have a VM but no deadline yet (just created or
+/// restarted) to run now, drop those whose VM is gone, and keep priorities
+/// in step with their bot. Instances in the middle of a run are left out
+/// until they report back.
+fn sync_schedule(
+    state: &Arc<Mutex<Vec<BotEntry>>>,
+    bots: &HashMap<String, Worker>,
+    scheduler: &mut Scheduler,
+) {
+    let entries = state.lock().unwrap();
+    for entry in entries.iter() {
+        for inst in &entry.instances {
+            if bots.get(&inst.id).is_some_and(|b| !b.busy) {
+                scheduler.admit(&inst.id, entry.priority);
+            }
+        }
+    }
+    scheduler.retain(|id| bots.contains_key(id));
+}
+
+/// Schedule the next run of an instance that ran cleanly and show its status.
+fn record_run(
+    state: &Arc<Mutex<Vec<BotEntry>>>,
+    scheduler: &mut Scheduler,
+    id: &str,
+    cd: f64,
+    status: Option<Status>,
+    lateness: Duration,
+) {
+    scheduler.schedule(id, Instant::now() + Duration::from_secs_f64(cd));
+    let mut entries = state.lock().unwrap();
+    if let Some(inst) = entries.iter_mut()
+        .flat_map(|e| e.instances.iter_mut())
+        .find(|i| i.id == id)
+    {
+        inst.status = status.unwrap_or_default();
+        inst.error = None;
+        inst.stuck = None;
+        inst.lateness = lateness;
+        if inst.last_error_at.is_some_and(|t| t.elapsed() >= RETRY_RESET) {
+            inst.retries = 0;
+        }
+    }
+}
+
+/// Start the bots when stopped, or stop them (aborting the running tick)
+/// when running. Does nothing while a stop is in progress.
+pub fn start_stop(orch_state: &Mutex<OrchestratorState>, cmd_tx: &mpsc::Sender<Command>) {
+    {
+        let mut os = orch_state.lock().unwrap();
+        match *os {
+            OrchestratorState::Running => {
+                // Abort the running tick now rather than after its delays
+                cancel::request();
+                *os = OrchestratorState::Stopping;
+            }
+            OrchestratorState::Stopped => *os = OrchestratorState::Running,
+            OrchestratorState::Stopping => return,
+        }
+    }
+    cmd_tx.send(Command::StartStop).ok();
+}
+
+/// Run one instance for a single pass outside the loop: start it, observe
+/// or tick once as the loop would, then stop it. Returns the cooldown the
+/// bot asked for and its status afterwards.
+pub fn run_once(entry: &BotEntry, inst: &Instance, platform: &dyn Platform) -> Result<(f64, Status), String> {
+    let failures: Failures = Arc::new(Mutex::new(Vec::new()));
+    let mut bot = LuaBot::new(
+        &entry.script_path, inst.info(&entry.name, entry.instances.len()),
+        platform.create_window(&entry.window_pattern, inst.window_id),
+        entry.option_values.resolve(&entry.options, &inst.key),
+        make_on_error(inst.id.clone(), Arc::clone(&failures)),
+    ).map_err(|e| e.to_string())?;
+
+    let result = (|| {
+        if bot.observes() {
+            bot.capture_frame();
+            if let Some(cd) = bot.observe().map_err(|e| e.to_string())? {
+                return Ok(cd);
+            }
+        }
+        bot.set_active(true);
+        if !bot.activate() {
+            bot.set_active(false);
+            return Err("window did not come to the front".to_string());
+        }
+        let cd = bot.tick().map_err(|e| e.to_string());
+        bot.set_active(false);
+        cd
+    })();
+    let status = bot.get_status().unwrap_or_default();
+    bot.stop().ok();
+    result.map(|cd| (cd, status))
+}
+
+/// Apply an instance thread's report: schedule its next run and show its
+/// status, or note that its window closed.
+fn handle_event(
+    event: Event,
+    state: &Arc<Mutex<Vec<BotEntry>>>,
+    bots: &mut HashMap<String, Worker>,
+    scheduler: &mut Scheduler,
+    usages: &mut Usages,
+    closed: &mut Vec<String>,
+) {
+    match event {
+        Event::Ran { id, serial, outcome, lateness, open } => {
+            let Some(bot) = bots.get_mut(&id).filter(|b| b.serial == serial) else { return };
+            bot.busy = false;
+            if !open {
+                closed.push(id.clone());
+            }
+            match outcome {
+                Outcome::Observed(cd, status) => record_run(state, scheduler, &id, cd, status, lateness),
+                Outcome::Ticked(cd, status) => {
+                    if let Some(usage) = usages.get_mut(bot.bot_name()) {
+                        usage.add(Local::now().date_naive(), Duration::ZERO, 1);
+                    }
+                    if cd > 60.0 {
+                         logger::info(&format!("next return for {}: {}", id, cd));
+                    }
+                    record_run(state, scheduler, &id, cd, status, lateness);
+                }
+                Outcome::Unfocused => {
+                    logger::warn_p(&id, "window did not come to the front, tick skipped");
+                    scheduler.schedule(&id, Instant::now() + FOCUS_RETRY);
+                }
+                // Failures were queued by on_error; handle_failures restarts
+                // or disables the instance
+                Outcome::Cancelled | Outcome::Failed => {}
+            }
+        }
+        Event::Checked { id, serial, open } => {
+            if !open && bots.get(&id).is_some_and(|b| b.serial == serial) {
+                closed.push(id);
+            }
+        }
+        Event::Woken { id, serial } => {
+            // A busy instance picks up its messages and actions in that run
+            if bots.get(&id).is_some_and(|b| b.serial == serial && !b.busy) {
+                scheduler.schedule(&id, Instant::now());
+            }
+        }
+        Event::Command(_) => {}
+    }
+}
+
+/// Main orchestration loop. Runs on a background thread; every bot
+/// instance runs on a thread of its own and reports back through events.
 pub fn orchestrate(
     state: Arc<Mutex<Vec<BotEntry>>>,
     orch_state: Arc<Mutex<OrchestratorState>>,
@@ -249,99 +790,140 @@ pub fn orchestrate(
     _bots_dir: PathBuf,
     cmd_rx: mpsc::Receiver<Command>,
 ) {
-    let mut bots: HashMap<String, LuaBot> = HashMap::new();
-    let mut cooldowns: HashMap<String, Instant> = HashMap::new();
+    // Commands and instance reports share one channel, so either wakes the loop
+    let (events, event_rx) = mpsc::channel();
+    let forward = events.clone();
+    std::thread::spawn(move || {
+        for cmd in cmd_rx {
+            if forward.send(Event::Command(cmd)).is_err() {
+                return;
+            }
+        }
+        forward.send(Event::Command(Command::Quit)).ok();
+    });
+
+    let mut bots: HashMap<String, Worker> = HashMap::new();
+    let mut scheduler = Scheduler::new();
+    let mut watcher = BotWatcher::new();
+    let reports = Reports { failures: Arc::new(Mutex::new(Vec::new())), events };
+    let mut restarts: HashMap<String, Instant> = HashMap::new();
+    let mut usages = Usages::new();
+    let mut last_pass = Instant::now();
+    let mut last_window_check = Instant::now();
+    // Order in which due instances get the foreground
+    let mut turn: u64 = 0;
+    // Settle holds before the first start so held bots are never created
+    apply_schedules(&state, platform.as_ref(), &reports, &mut bots, &mut usages, Duration::ZERO, false);
 
     loop {
-        std::thread::sleep(Duration::from_millis(100));
+        // Sleep until the next instance or restart is due, a command arrives
+        // or an instance reports back
+        let mut wake = Instant::now() + IDLE_WAIT;
+        if *orch_state.lock().unwrap() == OrchestratorState::Running {
+            let deadlines = scheduler.next_due().into_iter().chain(restarts.values().copied());
+            wake = deadlines.fold(wake, Instant::min);
+        }
+        let first = event_rx.recv_timeout(wake.saturating_duration_since(Instant::now())).ok();
 
-        if !process_commands(&cmd_rx, &state, &orch_state, platform.as_ref(), &mut bots, &mut cooldowns) {
+        // Instances whose window went away
+        let mut closed: Vec<String> = Vec::new();
+        let mut cmds = Vec::new();
+        for event in first.into_iter().chain(event_rx.try_iter()) {
+            match event {
+                Event::Command(cmd) => cmds.push(cmd),
+                event => handle_event(event, &state, &mut bots, &mut scheduler, &mut usages, &mut closed),
+            }
+        }
+
+        if !process_commands(cmds.into_iter(), &state, &orch_state, platform.as_ref(), &reports, &mut bots, &mut scheduler) {
             return;
         }
+        route_messages(&bots);
+        store::flush_due();
+        handle_failures(&reports.failures, &state, &mut bots, &mut scheduler, &mut restarts);
+
+        // Hot reload: pick up edits to bot files
+        let scripts: Vec<PathBuf> = state.lock().unwrap().iter()
+            .map(|e| e.script_path.clone())
+            .collect();
+        let changes = watcher.poll(&scripts, lua_rt::lib_dir());
+        let mut to_reload = changes.bots;
+        for file in &changes.lib {
+            // Reload every bot with a running instance that required the module
+            let entries = state.lock().unwrap();
+            for entry in entries.iter() {
+                let uses = entry.instances.iter()
+                    .any(|i| bots.get(&i.id).is_some_and(|b| b.requires(file)));
+                if uses && !to_reload.contains(&entry.script_path) {
+                    logger::info(&format!("{} changed, used by {}", file.display(), entry.name));
+                    to_reload.push(entry.script_path.clone());
+                }
+            }
+        }
+        for path in to_reload {
+            reload_bot(&path, &state, &orch_state, platform.as_ref(), &reports, &mut bots);
+        }
 
         // Skip tick processing when stopped
         let current = *orch_state.lock().unwrap();
         if current == OrchestratorState::Stopping {
-            // Graceful stop: tear down all bots, then transition to Stopped
-            for (_, mut bot) in bots.drain() {
-                bot.stop().ok();
-            }
-            cooldowns.clear();
+            // Graceful stop: the running ticks were aborted
+            worker::stop_all(bots.drain().map(|(_, bot)| bot));
+            scheduler.clear();
+            restarts.clear();
             *orch_state.lock().unwrap() = OrchestratorState::Stopped;
             logger::info("orchestrator stopped");
             continue;
         }
         if current != OrchestratorState::Running {
+            // Keep holds current so the TUI shows the next start
+            apply_schedules(&state, platform.as_ref(), &reports, &mut bots, &mut usages, Duration::ZERO, false);
+            last_pass = Instant::now();
             continue;
         }
 
-        // Collect ready instances
-        let ready: Vec<String> = {
-            let entries = state.lock().unwrap();
-            entries.iter()
-                .filter(|e| e.enabled)
-                .flat_map(|e| e.instances.iter())
-                .filter(|i| {
-                    bots.contains_key(&i.id)
-                        && cooldowns.get(&i.id).map_or(true, |t| Instant::now() >= *t)
-                })
-                .map(|i| i.id.clone())
-                .collect()
-        };
+        run_restarts(&state, platform.as_ref(), &reports, &mut bots, &mut restarts);
+        sync_schedule(&state, &bots, &mut scheduler);
 
-        for id in &ready {
-            // Stay responsive: check commands between each tick
-            if !process_commands(&cmd_rx, &state, &orch_state, platform.as_ref(), &mut bots, &mut cooldowns) {
-                return;
-            }
-
-            // If orchestrator was stopped mid-tick, break out
-            if *orch_state.lock().unwrap() != OrchestratorState::Running {
-                break;
-            }
-
-            let Some(bot) = bots.get_mut(id) else { continue };
-            bot.set_active(true);
-            bot.activate();
-            std::thread::sleep(Duration::from_millis(500));
-
-            let tick_result = bot.tick();
-            let status = if tick_result.is_ok() { bot.get_status().ok() } else { None };
-            bot.set_active(false);
+        // Hand every due instance to its thread. They observe side by side;
+        // those that tick take turns at the foreground in this order
+        while !cancel::requested() {
+            let Some((id, lateness)) = scheduler.pop_due(Instant::now()) else { break };
+            let Some(bot) = bots.get_mut(&id).filter(|b| !b.busy) else { continue };
+            turn += 1;
+            bot.run(turn, lateness);
+        }
 
-            if let Ok(s) = tick_result {
-                let cd = s.unwrap_or(5.0);
-                let cd = if cd.is_finite() && cd >= 0.0 { cd } else { 5.0 };
-                if cd > 60.0 {
-                     logger::info(&format!("next return for {}: {}", id, cd));
-                }
-                cooldowns.insert(id.clone(), Instant::now() + Duration::from_secs_f64(cd));
-                let mut entries = state.lock().unwrap();
-                if let Some(inst) = entries.iter_mut()
-                    .flat_map(|e| e.instances.iter_mut())
-                    .find(|i| i.id == *id)
-                {
-                    inst.status = status.unwrap_or_default();
-                    inst.error = None;
-                }
+        // Idle instances still hear about their window; busy ones check
+        // it after their run
+        if last_window_check.elapsed() >= WINDOW_CHECK {
+            last_window_check = Instant::now();
+            for bot in bots.values().filter(|b| !b.busy) {
+                bot.check_window();
             }
-            // On Err: on_error fired inside lua_rt; entry.enabled already set to false.
-            // The post-tick sweep below handles cleanup via the natural disable path.
         }
+        remove_closed(&state, &bots, &closed);
+        watch_stuck(&state, &mut bots);
+
+        // Holds set here take effect right away through the sweep below
+        apply_schedules(&state, platform.as_ref(), &reports, &mut bots, &mut usages, last_pass.elapsed(), true);
+        last_pass = Instant::now();
 
-        // Sweep bots whose entry was disabled (e.g. by a runtime error via on_error).
-        // This is the single removal path — the same one Toggle-disable uses.
+        // Sweep bots whose entry or instance was disabled (e.g. once its
+        // restarts ran out), whose window closed, or whose entry is held by
+        // its schedule. This is the single removal path — the same one
+        // Toggle-disable uses.
         let disabled_ids: Vec<String> = {
             let entries = state.lock().unwrap();
             bots.keys()
                 .filter(|id| !entries.iter()
-                    .any(|e| e.enabled && e.instances.iter().any(|i| &i.id == *id)))
+                    .any(|e| e.enabled && e.hold.is_none() && e.instances.iter().any(|i| i.enabled && &i.id == *id)))
                 .cloned()
                 .collect()
         };
         for id in disabled_ids {
-            if let Some(mut bot) = bots.remove(&id) { bot.stop().ok(); }
-            cooldowns.remove(&id);
+            if let Some(bot) = bots.remove(&id) { bot.stop(); }
+            scheduler.remove(&id);
         }
     }
 }
@@ -1,5 +1,5 @@
 use std::process::Command as ProcessCommand;
-use std::sync::Arc;
+use std::sync::{Arc, mpsc};
 use std::sync::atomic::{AtomicBool, Ordering};
 
 use core_foundation::array::CFArray;
@@ -15,7 +15,7 @@ use core_graphics::window::*;
 use crate::logger;
 use crate::sleep;
 use crate::types::*;
-use super::{Platform, WindowHandle};
+use super::{KeyPause, Platform, WindowHandle};
 
 // AppleScript key codes for special keys
 fn applescript_key_code(key: &str) -> Option<u16> {
@@ -33,6 +33,32 @@ fn applescript_key_code(key: &str) -> Option<u16> {
     }
 }
 
+// Key name in `win:tap` syntax for a recorded ANSI virtual key code
+fn recorded_key_name(keycode: i64) -> Option<&'static str> {
+    const LETTERS: [(i64, &str); 36] = [
+        (0, "a"), (11, "b"), (8, "c"), (2, "d"), (14, "e"), (3, "f"), (5, "g"),
+        (4, "h"), (34, "i"), (38, "j"), (40, "k"), (37, "l"), (46, "m"), (45, "n"),
+        (31, "o"), (35, "p"), (12, "q"), (15, "r"), (1, "s"), (17, "t"), (32, "u"),
+        (9, "v"), (13, "w"), (7, "x"), (16, "y"), (6, "z"),
+        (29, "0"), (18, "1"), (19, "2"), (20, "3"), (21, "4"), (23, "5"),
+        (22, "6"), (26, "7"), (28, "8"), (25, "9"),
+    ];
+    match keycode {
+        36 => Some("enter"),
+        53 => Some("escape"),
+        51 => Some("backspace"),
+        48 => Some("tab"),
+        49 => Some("space"),
+        126 => Some("up"),
+        125 => Some("down"),
+        123 => Some("left"),
+        124 => Some("right"),
+        24 => Some("="),
+        27 => Some("-"),
+        _ => LETTERS.iter().find(|(code, _)| *code == keycode).map(|(_, name)| *name),
+    }
+}
+
 pub struct DarwinPlatform;
 
 impl DarwinPlatform {
@@ -170,6 +196,7 @@ impl Platform for DarwinPlatform {
                 if keycode == KEYCODE_K && has_cmd && has_shift && no_alt && no_ctrl {
                     let flag = &*(user_info as *const AtomicBool);
                     flag.store(true, Ordering::Release);
+                    crate::cancel::request();
                 }
                 event
             }
@@ -197,6 +224,123 @@ impl Platform for DarwinPlatform {
         });
     }
 
+    fn start_input_recorder(&self, tx: mpsc::Sender<InputEvent>, stop: Arc<AtomicBool>) {
+        use std::ffi::c_void;
+
+        type CGEventTapProxy = *mut c_void;
+        type CGEventRef = *mut c_void;
+        type CFMachPortRef = *mut c_void;
+        type CFRunLoopSourceRef = *mut c_void;
+        type CFRunLoopRef = *mut c_void;
+        type CFStringRef = *const c_void;
+        type CGEventMask = u64;
+        type CGEventType = u32;
+        type CGEventFlags = u64;
+
+        type CGEventTapCallBack = unsafe extern "C" fn(
+            CGEventTapProxy, CGEventType, CGEventRef, *mut c_void,
+        ) -> CGEventRef;
+
+        #[repr(C)]
+        struct Point { x: f64, y: f64 }
+
+        const K_CG_HID_EVENT_TAP: u32 = 0;
+        const K_CG_HEAD_INSERT_EVENT_TAP: u32 = 0;
+        const K_CG_EVENT_TAP_OPTION_LISTEN_ONLY: u32 = 1;
+        const CG_EVENT_LEFT_MOUSE_DOWN: u32 = 1;
+        const CG_EVENT_KEY_DOWN: u32 = 10;
+        const K_CG_EVENT_FLAG_MASK_ALTERNATE: u64 = 0x00080000;
+        const K_CG_EVENT_FLAG_MASK_SHIFT: u64 = 0x00020000;
+        const K_CG_EVENT_FLAG_MASK_COMMAND: u64 = 0x00100000;
+        const K_CG_EVENT_FLAG_MASK_CONTROL: u64 = 0x00040000;
+        const K_CG_KEYBOARD_EVENT_AUTOREPEAT: u32 = 8;
+        const K_CG_KEYBOARD_EVENT_KEYCODE: u32 = 9;
+
+        extern "C" {
+            fn CGEventTapCreate(
+                tap: u32, place: u32, options: u32,
+                events_of_interest: CGEventMask,
+                callback: CGEventTapCallBack,
+                user_info: *mut c_void,
+            ) -> CFMachPortRef;
+            fn CFMachPortCreateRunLoopSource(
+                allocator: *const c_void, port: CFMachPortRef, order: i64,
+            ) -> CFRunLoopSourceRef;
+            fn CFMachPortInvalidate(port: CFMachPortRef);
+            fn CFRelease(cf: *const c_void);
+            fn CFRunLoopGetCurrent() -> CFRunLoopRef;
+            fn CFRunLoopAddSource(rl: CFRunLoopRef, source: CFRunLoopSourceRef, mode: CFStringRef);
+            fn CFRunLoopRunInMode(mode: CFStringRef, seconds: f64, return_after_source_handled: u8) -> i32;
+            fn CGEventGetFlags(event: CGEventRef) -> CGEventFlags;
+            fn CGEventGetIntegerValueField(event: CGEventRef, field: u32) -> i64;
+            fn CGEventGetLocation(event: CGEventRef) -> Point;
+            fn CGEventTapEnable(tap: CFMachPortRef, enable: bool);
+            static kCFRunLoopDefaultMode: CFStringRef;
+        }
+
+        unsafe extern "C" fn record_callback(
+            _proxy: CGEventTapProxy, event_type: CGEventType,
+            event: CGEventRef, user_info: *mut c_void,
+        ) -> CGEventRef {
+            unsafe {
+                let input = match event_type {
+                    CG_EVENT_LEFT_MOUSE_DOWN => {
+                        let p = CGEventGetLocation(event);
+                        Some(InputEvent::Click { x: p.x as i32, y: p.y as i32 })
+                    }
+                    CG_EVENT_KEY_DOWN
+                        if CGEventGetIntegerValueField(event, K_CG_KEYBOARD_EVENT_AUTOREPEAT) == 0 =>
+                    {
+                        let keycode = CGEventGetIntegerValueField(event, K_CG_KEYBOARD_EVENT_KEYCODE);
+                        let flags = CGEventGetFlags(event);
+                        recorded_key_name(keycode).map(|key| {
+                            let mut parts = Vec::new();
+                            if flags & K_CG_EVENT_FLAG_MASK_COMMAND != 0 { parts.push("cmd"); }
+                            if flags & K_CG_EVENT_FLAG_MASK_CONTROL != 0 { parts.push("ctrl"); }
+                            if flags & K_CG_EVENT_FLAG_MASK_ALTERNATE != 0 { parts.push("alt"); }
+                            if flags & K_CG_EVENT_FLAG_MASK_SHIFT != 0 { parts.push("shift"); }
+                            parts.push(key);
+                            InputEvent::Key(parts.join("+"))
+                        })
+                    }
+                    _ => None,
+                };
+                if let Some(input) = input {
+                    let tx = &*(user_info as *const mpsc::Sender<InputEvent>);
+                    let _ = tx.send(input);
+                }
+                event
+            }
+        }
+
+        std::thread::spawn(move || unsafe {
+            let mask: CGEventMask = (1 << CG_EVENT_LEFT_MOUSE_DOWN) | (1 << CG_EVENT_KEY_DOWN);
+            let tx_ptr = Box::into_raw(Box::new(tx)) as *mut c_void;
+            let tap = CGEventTapCreate(
+                K_CG_HID_EVENT_TAP, K_CG_HEAD_INSERT_EVENT_TAP,
+                K_CG_EVENT_TAP_OPTION_LISTEN_ONLY, mask, record_callback, tx_ptr,
+            );
+            if tap.is_null() {
+                crate::logger::error(
+                    "failed to create event tap — grant Accessibility permission to your terminal",
+                );
+                drop(Box::from_raw(tx_ptr as *mut mpsc::Sender<InputEvent>));
+                return;
+            }
+            let source = CFMachPortCreateRunLoopSource(std::ptr::null(), tap, 0);
+            CFRunLoopAddSource(CFRunLoopGetCurrent(), source, kCFRunLoopDefaultMode);
+            CGEventTapEnable(tap, true);
+            // Unlike the hotkey tap this one ends, so poll `stop` between short runs
+            while !stop.load(Ordering::Acquire) {
+                CFRunLoopRunInMode(kCFRunLoopDefaultMode, 0.1, 0);
+            }
+            CFMachPortInvalidate(tap);
+            CFRelease(source);
+            CFRelease(tap);
+            drop(Box::from_raw(tx_ptr as *mut mpsc::Sender<InputEvent>));
+        });
+    }
+
     fn activate_terminal(&self) {
         let ppid = unsafe { libc::getppid() };
         let script = format!(
@@ -289,30 +433,37 @@ impl WindowHandle for DarwinWindow {
         self.do_update();
     }
 
-    fn activate(&mut self) {
+    fn activate(&mut self) -> bool {
         if self.pid.is_none() {
             self.do_update();
         }
-        if let Some(pid) = self.pid {
-            let script = format!(
-                "tell application \"System Events\" to set frontmost of first process whose unix id is {} to true",
-                pid
-            );
-            ProcessCommand::new("osascript")
-                .arg("-e")
-                .arg(&script)
-                .stdout(std::process::Stdio::null())
-                .stderr(std::process::Stdio::null())
-                .status()
-                .ok();
+        let Some(pid) = self.pid else { return false };
+        if frontmost_pid() == Some(pid) {
+            return false;
         }
+        let script = format!(
+            "tell application \"System Events\" to set frontmost of first process whose unix id is {} to true",
+            pid
+        );
+        ProcessCommand::new("osascript")
+            .arg("-e")
+            .arg(&script)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .ok();
+        true
     }
 
-    fn click_relative(&mut self, x_ratio: f64, y_ratio: f64) {
+    fn is_foreground(&self) -> bool {
+        self.pid.is_some() && frontmost_pid() == self.pid
+    }
+
+    fn click_relative(&mut self, x_ratio: f64, y_ratio: f64) -> Result<(), InputError> {
         self.do_update();
         let region = match self.region {
             Some(r) => r,
-            None => return,
+            None => return Err(InputError::Closed),
         };
 
         let x = region.l as f64 + x_ratio * region.w as f64;
@@ -321,20 +472,22 @@ impl WindowHandle for DarwinWindow {
 
         let source = match CGEventSource::new(CGEventSourceStateID::HIDSystemState) {
             Ok(s) => s,
-            Err(_) => return,
+            Err(_) => return Err(InputError::Failed),
         };
 
-        if let Ok(mouse_down) = CGEvent::new_mouse_event(
+        match CGEvent::new_mouse_event(
             source.clone(),
             CGEventType::LeftMouseDown,
             point,
             CGMouseButton::Left,
         ) {
-            mouse_down.post(CGEventTapLocation::HID);
+            Ok(mouse_down) => mouse_down.post(CGEventTapLocation::HID),
+            Err(_) => return Err(InputError::Failed),
         }
 
-        std::thread::sleep(std::time::Duration::from_millis(15));
+        sleep::ms(15);
 
+        // Always release, even when a stop request cut the press short
         if let Ok(mouse_up) = CGEvent::new_mouse_event(
             source,
             CGEventType::LeftMouseUp,
@@ -344,17 +497,54 @@ impl WindowHandle for DarwinWindow {
             mouse_up.post(CGEventTapLocation::HID);
         }
 
-        std::thread::sleep(std::time::Duration::from_millis(15));
+        sleep::ms(15);
+        Ok(())
+    }
+
+    fn move_relative(&mut self, x_ratio: f64, y_ratio: f64) {
+        // Called once per path step, so reuse the cached region
+        if self.region.is_none() {
+            self.do_update();
+        }
+        let Some(region) = self.region else { return };
+
+        let x = region.l as f64 + x_ratio * region.w as f64;
+        let y = region.t as f64 + y_ratio * region.h as f64;
+        let Ok(source) = CGEventSource::new(CGEventSourceStateID::HIDSystemState) else {
+            return;
+        };
+        if let Ok(moved) = CGEvent::new_mouse_event(
+            source,
+            CGEventType::MouseMoved,
+            CGPoint::new(x, y),
+            CGMouseButton::Left,
+        ) {
+            moved.post(CGEventTapLocation::HID);
+        }
+    }
+
+    fn tap(&mut self, key: &str) -> Result<(), InputError> {
+        self.tap_timed(key, &mut |pause| match pause {
+            KeyPause::Release => sleep::jitter(50, 0.3),
+            _ => 0,
+        })
     }
 
-    fn tap(&mut self, key: &str) {
+    /// System Events presses and releases the key in one go, so only the
+    /// pauses before and after it can be chosen
+    fn tap_timed(&mut self, key: &str, pause: &mut dyn FnMut(KeyPause) -> u64) -> Result<(), InputError> {
+        sleep::ms(pause(KeyPause::Lead));
+        if crate::cancel::requested() {
+            return Err(InputError::Cancelled);
+        }
+
         let pid = match self.pid {
             Some(p) => p,
             None => {
                 self.do_update();
                 match self.pid {
                     Some(p) => p,
-                    None => return,
+                    None => return Err(InputError::Closed),
                 }
             }
         };
@@ -394,7 +584,7 @@ impl WindowHandle for DarwinWindow {
             format!("keystroke \"{}\"", escaped)
         } else {
             logger::warn_p("darwin", &format!("unknown key: {}", main_key));
-            return;
+            return Err(InputError::UnknownKey);
         };
 
         let modifier_str = if modifiers.is_empty() {
@@ -408,21 +598,31 @@ impl WindowHandle for DarwinWindow {
             pid, key_part, modifier_str
         );
 
-        ProcessCommand::new("osascript")
+        let sent = ProcessCommand::new("osascript")
             .arg("-e")
             .arg(&script)
             .stdout(std::process::Stdio::null())
             .stderr(std::process::Stdio::null())
             .status()
-            .ok();
+            .is_ok_and(|s| s.success());
 
-        sleep::jittered_ms(50, 0.3);
+        sleep::ms(pause(KeyPause::Release));
+        if !sent {
+            // System Events fails on a process that has gone away
+            self.do_update();
+            return Err(if self.region.is_none() { InputError::Closed } else { InputError::Failed });
+        }
+        Ok(())
     }
 
-    fn type_text(&mut self, text: &str) {
+    fn type_text(&mut self, text: &str) -> Result<(), InputError> {
         for ch in text.chars() {
-            self.tap(&ch.to_string());
+            if crate::cancel::requested() {
+                return Err(InputError::Cancelled);
+            }
+            self.tap(&ch.to_string())?;
         }
+        Ok(())
     }
 
     fn capture(&mut self, rect: Option<CaptureRect>) -> Option<Capture> {
@@ -480,6 +680,28 @@ unsafe fn get_cf_string(
     Some(cf_str.to_string())
 }
 
+/// Owner of the frontmost normal window. Activation raises a whole process,
+/// so this is what "foreground" means for a window here.
+fn frontmost_pid() -> Option<i32> {
+    unsafe {
+        let option = kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements;
+        let window_list_ref = CGWindowListCopyWindowInfo(option, kCGNullWindowID);
+        if window_list_ref.is_null() {
+            return None;
+        }
+        let list: CFArray = CFArray::wrap_under_create_rule(window_list_ref as _);
+        // The list runs front to back; menu bar and overlays sit above layer 0
+        list.get_all_values().iter().find_map(|dict_ptr| {
+            let dict: CFDictionary<CFString, *const std::ffi::c_void> =
+                CFDictionary::wrap_under_get_rule(*dict_ptr as _);
+            match get_cf_number(&dict, "kCGWindowLayer") {
+                Some(0) => get_cf_number(&dict, "kCGWindowOwnerPID").map(|v| v as i32),
+                _ => None,
+            }
+        })
+    }
+}
+
 unsafe fn get_cf_number(
     dict: &CFDictionary<CFString, *const std::ffi::c_void>,
     key: &str,
@@ -6,7 +6,7 @@ pub mod darwin;
 #[cfg(target_os = "windows")]
 pub mod win32;
 
-use std::sync::Arc;
+use std::sync::{Arc, mpsc};
 use std::sync::atomic::AtomicBool;
 
 use crate::types::*;
@@ -18,11 +18,38 @@ pub trait WindowHandle: Send {
     fn title(&self) -> &str;
     fn region(&self) -> Option<Region>;
     fn update(&mut self);
-    fn activate(&mut self);
-    fn click_relative(&mut self, x_ratio: f64, y_ratio: f64);
-    fn tap(&mut self, key: &str);
-    fn type_text(&mut self, text: &str);
+    /// Bring the window to the front. Returns false when it already was,
+    /// so callers can skip waiting for the switch to settle.
+    fn activate(&mut self) -> bool;
+    /// Whether the window currently has the foreground.
+    fn is_foreground(&self) -> bool;
+    /// Input methods return why the input did not go through, e.g. the
+    /// window closed; success only means the OS accepted the events.
+    fn click_relative(&mut self, x_ratio: f64, y_ratio: f64) -> Result<(), InputError>;
+    fn tap(&mut self, key: &str) -> Result<(), InputError>;
+    fn type_text(&mut self, text: &str) -> Result<(), InputError>;
     fn capture(&mut self, rect: Option<CaptureRect>) -> Option<Capture>;
+    /// Move the cursor without clicking; used to draw humanized mouse paths.
+    fn move_relative(&mut self, _x_ratio: f64, _y_ratio: f64) {}
+    /// Tap a key, taking the length of every pause in the press from
+    /// `pause` instead of the platform's own jitter. Platforms without
+    /// control over the timing fall back to a plain tap.
+    fn tap_timed(&mut self, key: &str, _pause: &mut dyn FnMut(KeyPause) -> u64) -> Result<(), InputError> {
+        self.tap(key)
+    }
+}
+
+/// The pauses in one key press, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPause {
+    /// Before anything is pressed
+    Lead,
+    /// After each modifier goes down or up
+    Modifier,
+    /// Main key held down
+    Hold,
+    /// After the main key is released
+    Release,
 }
 
 /// Platform-level operations (window enumeration, factory).
@@ -33,6 +60,9 @@ pub trait Platform: Send {
     fn start_hotkey_listener(&self, flag: Arc<AtomicBool>);
     /// Bring the terminal / launcher window that owns this process to the foreground.
     fn activate_terminal(&self);
+    /// Start a background thread reporting the user's clicks and key presses
+    /// on `tx` until `stop` is set.
+    fn start_input_recorder(&self, tx: mpsc::Sender<InputEvent>, stop: Arc<AtomicBool>);
 }
 
 /// Create the platform appropriate for the current OS.
@@ -1,9 +1,13 @@
-use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, mpsc};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
 
 use crate::types::*;
 use crate::logger;
-use super::{Platform, WindowHandle};
+use super::{KeyPause, Platform, WindowHandle};
+
+/// Stub window currently "in front"
+static FOREGROUND: AtomicU64 = AtomicU64::new(0);
 
 pub struct StubPlatform;
 
@@ -42,6 +46,25 @@ impl Platform for StubPlatform {
     fn activate_terminal(&self) {
         logger::info_p("stub", "activate_terminal (no-op)");
     }
+
+    /// Replays a fixed demo sequence inside the 1920x1080 stub window.
+    fn start_input_recorder(&self, tx: mpsc::Sender<InputEvent>, stop: Arc<AtomicBool>) {
+        logger::info_p("stub", "start_input_recorder (demo sequence)");
+        let demo = [
+            InputEvent::Click { x: 960, y: 540 },
+            InputEvent::Key("space".into()),
+            InputEvent::Click { x: 1632, y: 810 },
+            InputEvent::Key("ctrl+a".into()),
+        ];
+        std::thread::spawn(move || {
+            for event in demo {
+                std::thread::sleep(Duration::from_millis(400));
+                if stop.load(Ordering::Acquire) || tx.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+    }
 }
 
 struct StubWindow {
@@ -55,24 +78,37 @@ impl WindowHandle for StubWindow {
     fn title(&self) -> &str { &self.title }
     fn region(&self) -> Option<Region> { Some(self.region) }
 
-    fn update(&mut self) {
-        logger::info_p("stub", &format!("win({}).update()", self.window_id));
+    // Not logged: the orchestrator refreshes windows every couple of seconds
+    fn update(&mut self) {}
+
+    fn activate(&mut self) -> bool {
+        let changed = FOREGROUND.swap(self.window_id, Ordering::AcqRel) != self.window_id;
+        logger::info_p("stub", &format!("win({}).activate(){}", self.window_id, if changed { "" } else { " already in front" }));
+        changed
     }
 
-    fn activate(&mut self) {
-        logger::info_p("stub", &format!("win({}).activate()", self.window_id));
+    fn is_foreground(&self) -> bool {
+        FOREGROUND.load(Ordering::Acquire) == self.window_id
     }
 
-    fn click_relative(&mut self, x_ratio: f64, y_ratio: f64) {
+    fn click_relative(&mut self, x_ratio: f64, y_ratio: f64) -> Result<(), InputError> {
         logger::info_p("stub", &format!("win({}).click_relative({:.2}, {:.2})", self.window_id, x_ratio, y_ratio));
+        Ok(())
     }
 
-    fn tap(&mut self, key: &str) {
+    fn tap(&mut self, key: &str) -> Result<(), InputError> {
         logger::info_p("stub", &format!("win({}).tap(\"{}\")", self.window_id, key));
+        Ok(())
+    }
+
+    fn tap_timed(&mut self, key: &str, pause: &mut dyn FnMut(KeyPause) -> u64) -> Result<(), InputError> {
+        logger::info_p("stub", &format!("win({}).tap(\"{}\") held {}ms", self.window_id, key, pause(KeyPause::Hold)));
+        Ok(())
     }
 
-    fn type_text(&mut self, text: &str) {
+    fn type_text(&mut self, text: &str) -> Result<(), InputError> {
         logger::info_p("stub", &format!("win({}).type_text(\"{}\")", self.window_id, text));
+        Ok(())
     }
 
     fn capture(&mut self, rect: Option<CaptureRect>) -> Option<Capture> {
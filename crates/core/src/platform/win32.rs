@@ -1,6 +1,4 @@
-use std::thread::sleep;
-use std::time::Duration;
-use std::sync::Arc;
+use std::sync::{Arc, mpsc};
 use std::sync::atomic::{AtomicBool, Ordering};
 
 use windows::Win32::Foundation::*;
@@ -11,7 +9,7 @@ use windows::Win32::UI::WindowsAndMessaging::*;
 use crate::logger;
 use crate::sleep;
 use crate::types::*;
-use super::{Platform, WindowHandle};
+use super::{KeyPause, Platform, WindowHandle};
 
 // PrintWindow is in user32 / gdi32 but not always re-exported; declare directly.
 extern "system" {
@@ -58,6 +56,29 @@ fn vk_code(key: &str) -> Option<u16> {
     }
 }
 
+// Key name in `win:tap` syntax for a recorded virtual key (inverse of vk_code)
+fn recorded_key_name(vk: u32) -> Option<String> {
+    let name = match vk {
+        0x0D => "enter",
+        0x26 => "up",
+        0x28 => "down",
+        0x25 => "left",
+        0x27 => "right",
+        0x1B => "escape",
+        0x20 => "space",
+        0x09 => "tab",
+        0x08 => "backspace",
+        0x2E => "delete",
+        0xBB => "=",
+        0xBD => "-",
+        0x30..=0x39 | 0x41..=0x5A => {
+            return Some((vk as u8 as char).to_ascii_lowercase().to_string());
+        }
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
 // Parse "ctrl+shift+k" -> (modifiers_vk, main_key_str)
 fn parse_key(key: &str) -> (Vec<u16>, String) {
     let parts: Vec<&str> = key.split('+').collect();
@@ -244,11 +265,157 @@ impl Platform for Win32Platform {
             while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
                 if msg.message == WM_HOTKEY && msg.w_param == HOTKEY_ID as usize {
                     flag.store(true, Ordering::Release);
+                    crate::cancel::request();
                 }
             }
         });
     }
 
+    fn start_input_recorder(&self, tx: mpsc::Sender<InputEvent>, stop: Arc<AtomicBool>) {
+        use std::cell::RefCell;
+        use std::collections::HashSet;
+        use std::ffi::c_void;
+
+        type HHOOK     = *mut c_void;
+        type HINSTANCE = *mut c_void;
+        type HWND      = *mut c_void;
+        type BOOL      = i32;
+        type UINT      = u32;
+        type WPARAM    = usize;
+        type LPARAM    = isize;
+        type LRESULT   = isize;
+        type DWORD     = u32;
+        type LONG      = i32;
+        type HOOKPROC  = unsafe extern "system" fn(i32, WPARAM, LPARAM) -> LRESULT;
+
+        #[repr(C)] struct POINT { x: LONG, y: LONG }
+        #[repr(C)] struct MSG {
+            hwnd:    HWND,
+            message: UINT,
+            w_param: WPARAM,
+            l_param: LPARAM,
+            time:    DWORD,
+            pt:      POINT,
+        }
+        #[repr(C)] struct MSLLHOOKSTRUCT {
+            pt:         POINT,
+            mouse_data: DWORD,
+            flags:      DWORD,
+            time:       DWORD,
+            extra_info: usize,
+        }
+        #[repr(C)] struct KBDLLHOOKSTRUCT {
+            vk_code:    DWORD,
+            scan_code:  DWORD,
+            flags:      DWORD,
+            time:       DWORD,
+            extra_info: usize,
+        }
+
+        const WH_KEYBOARD_LL: i32    = 13;
+        const WH_MOUSE_LL:    i32    = 14;
+        const WM_KEYDOWN:     WPARAM = 0x0100;
+        const WM_KEYUP:       WPARAM = 0x0101;
+        const WM_SYSKEYDOWN:  WPARAM = 0x0104;
+        const WM_SYSKEYUP:    WPARAM = 0x0105;
+        const WM_LBUTTONDOWN: WPARAM = 0x0201;
+        const LLKHF_INJECTED: DWORD  = 0x10;
+        const LLMHF_INJECTED: DWORD  = 0x01;
+        const PM_REMOVE:      UINT   = 0x0001;
+        // Checked in this order to build "ctrl+alt+shift+k"
+        const MODIFIERS: [(i32, &str); 3] = [(0x11, "ctrl"), (0x12, "alt"), (0x10, "shift")];
+
+        extern "system" {
+            fn SetWindowsHookExW(id: i32, hook: HOOKPROC, hmod: HINSTANCE, thread_id: DWORD) -> HHOOK;
+            fn UnhookWindowsHookEx(hook: HHOOK) -> BOOL;
+            fn CallNextHookEx(hook: HHOOK, code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT;
+            fn PeekMessageW(
+                msg: *mut MSG, hwnd: HWND,
+                msg_filter_min: UINT, msg_filter_max: UINT, remove: UINT,
+            ) -> BOOL;
+            fn GetModuleHandleW(name: *const u16) -> HINSTANCE;
+            fn GetAsyncKeyState(vk: i32) -> i16;
+        }
+
+        // Low-level hook procs take no user data; they run on the thread
+        // that installed them, so keep the sender and held keys there.
+        thread_local! {
+            static SENDER: RefCell<Option<mpsc::Sender<InputEvent>>> = const { RefCell::new(None) };
+            static HELD: RefCell<HashSet<u32>> = RefCell::new(HashSet::new());
+        }
+
+        fn emit(input: InputEvent) {
+            SENDER.with(|s| {
+                if let Some(tx) = s.borrow().as_ref() {
+                    let _ = tx.send(input);
+                }
+            });
+        }
+
+        // Injected input (our own bots) is not the user's and is skipped
+        unsafe extern "system" fn mouse_proc(code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+            unsafe {
+                if code >= 0 && w_param == WM_LBUTTONDOWN {
+                    let info = &*(l_param as *const MSLLHOOKSTRUCT);
+                    if info.flags & LLMHF_INJECTED == 0 {
+                        emit(InputEvent::Click { x: info.pt.x, y: info.pt.y });
+                    }
+                }
+                CallNextHookEx(std::ptr::null_mut(), code, w_param, l_param)
+            }
+        }
+
+        unsafe extern "system" fn keyboard_proc(code: i32, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+            unsafe {
+                let info = &*(l_param as *const KBDLLHOOKSTRUCT);
+                if code >= 0 && info.flags & LLKHF_INJECTED == 0 {
+                    match w_param {
+                        WM_KEYDOWN | WM_SYSKEYDOWN => {
+                            // Auto-repeat sends more key-downs without a key-up
+                            let first = HELD.with(|h| h.borrow_mut().insert(info.vk_code));
+                            if let (true, Some(key)) = (first, recorded_key_name(info.vk_code)) {
+                                let mut parts: Vec<&str> = MODIFIERS.iter()
+                                    .filter(|(vk, _)| GetAsyncKeyState(*vk) < 0)
+                                    .map(|(_, name)| *name)
+                                    .collect();
+                                parts.push(&key);
+                                emit(InputEvent::Key(parts.join("+")));
+                            }
+                        }
+                        WM_KEYUP | WM_SYSKEYUP => {
+                            HELD.with(|h| h.borrow_mut().remove(&info.vk_code));
+                        }
+                        _ => {}
+                    }
+                }
+                CallNextHookEx(std::ptr::null_mut(), code, w_param, l_param)
+            }
+        }
+
+        std::thread::spawn(move || unsafe {
+            SENDER.with(|s| *s.borrow_mut() = Some(tx));
+            let hmod = GetModuleHandleW(std::ptr::null());
+            let mouse = SetWindowsHookExW(WH_MOUSE_LL, mouse_proc, hmod, 0);
+            let keyboard = SetWindowsHookExW(WH_KEYBOARD_LL, keyboard_proc, hmod, 0);
+            if mouse.is_null() || keyboard.is_null() {
+                crate::logger::error("failed to install input hooks for recording");
+            } else {
+                // Hooks are only called while this thread pumps messages
+                let mut msg: MSG = std::mem::zeroed();
+                while !stop.load(Ordering::Acquire) {
+                    while PeekMessageW(&mut msg, std::ptr::null_mut(), 0, 0, PM_REMOVE) != 0 {}
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+            }
+            if !mouse.is_null() {
+                UnhookWindowsHookEx(mouse);
+            }
+            if !keyboard.is_null() {
+                UnhookWindowsHookEx(keyboard);
+            }
+        });
+    }
+
     fn activate_terminal(&self) {
         use std::ffi::c_void;
         type HWND = *mut c_void;
@@ -320,26 +487,84 @@ impl Win32Window {
         }
     }
 
-    fn send_vk(&self, vk: u16) {
+    /// Returns false if the key press was blocked (e.g. by UIPI).
+    fn send_vk(&self, vk: u16, pause: &mut dyn FnMut(KeyPause) -> u64) -> bool {
         unsafe {
             let down = key_input(vk, false);
             let up   = key_input(vk, true);
-            SendInput(&[down], std::mem::size_of::<INPUT>() as i32);
-            sleep::jittered_ms(20, 0.3);
+            if SendInput(&[down], std::mem::size_of::<INPUT>() as i32) == 0 {
+                return false;
+            }
+            sleep::ms(pause(KeyPause::Hold));
             SendInput(&[up],   std::mem::size_of::<INPUT>() as i32);
-            sleep::jittered_ms(20, 0.3);
+            sleep::ms(pause(KeyPause::Release));
+            true
         }
     }
 
-    fn send_unicode_char(&self, ch: u16) {
+    fn send_unicode_char(&self, ch: u16, pause: &mut dyn FnMut(KeyPause) -> u64) -> bool {
         unsafe {
             let down = unicode_input(ch, false);
             let up   = unicode_input(ch, true);
-            SendInput(&[down], std::mem::size_of::<INPUT>() as i32);
-            sleep::jittered_ms(20, 0.3);
+            if SendInput(&[down], std::mem::size_of::<INPUT>() as i32) == 0 {
+                return false;
+            }
+            sleep::ms(pause(KeyPause::Hold));
             SendInput(&[up],   std::mem::size_of::<INPUT>() as i32);
-            sleep::jittered_ms(20, 0.3);
+            sleep::ms(pause(KeyPause::Release));
+            true
+        }
+    }
+
+    fn press(&mut self, key: &str, pause: &mut dyn FnMut(KeyPause) -> u64) -> Result<(), InputError> {
+        if self.hwnd.is_null() || unsafe { !IsWindow(self.hwnd.hwnd()).as_bool() } {
+            return Err(InputError::Closed);
+        }
+        sleep::ms(pause(KeyPause::Lead));
+        if crate::cancel::requested() {
+            return Err(InputError::Cancelled);
+        }
+
+        let (mods, main_key) = parse_key(key);
+
+        // Press modifiers
+        unsafe {
+            for &m in &mods {
+                let input = key_input(m, false);
+                SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+                sleep::ms(pause(KeyPause::Modifier));
+            }
+        }
+
+        // Press main key (VK or Unicode)
+        let result = if let Some(vk) = vk_code(&main_key.to_lowercase()) {
+            if self.send_vk(vk, pause) { Ok(()) } else { Err(InputError::Failed) }
+        } else if main_key.len() == 1 {
+            // Fallback: treat single unknown char as unicode
+            let ch = main_key.chars().next().unwrap() as u16;
+            if self.send_unicode_char(ch, pause) { Ok(()) } else { Err(InputError::Failed) }
+        } else {
+            logger::warn_p("win32", &format!("unknown key: {}", main_key));
+            Err(InputError::UnknownKey)
+        };
+
+        // Release modifiers in reverse
+        unsafe {
+            for &m in mods.iter().rev() {
+                let input = key_input(m, true);
+                SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+                sleep::ms(pause(KeyPause::Modifier));
+            }
         }
+        result
+    }
+}
+
+/// Pauses of a key press outside the humanizer
+fn default_pause(pause: KeyPause) -> u64 {
+    match pause {
+        KeyPause::Lead => sleep::jitter(100, 0.3),
+        _ => sleep::jitter(20, 0.3),
     }
 }
 
@@ -360,14 +585,22 @@ impl WindowHandle for Win32Window {
         self.do_update();
     }
 
-    fn activate(&mut self) {
+    fn activate(&mut self) -> bool {
         if self.region.is_none() {
             self.do_update();
         }
+        if self.is_foreground() {
+            return false;
+        }
         self.do_activate();
+        true
+    }
+
+    fn is_foreground(&self) -> bool {
+        unsafe { GetForegroundWindow() == self.hwnd.hwnd() }
     }
 
-    fn click_relative(&mut self, x_ratio: f64, y_ratio: f64) {
+    fn click_relative(&mut self, x_ratio: f64, y_ratio: f64) -> Result<(), InputError> {
         self.do_update();
         let region = match self.region {
             Some(r) => r,
@@ -375,68 +608,66 @@ impl WindowHandle for Win32Window {
                 logger::warn_p("win32", &format!(
                     "[{}]: window not found for click_relative", self.title
                 ));
-                return;
+                return Err(InputError::Closed);
             }
         };
 
         let screen_x = region.l + (x_ratio * region.w as f64) as i32;
         let screen_y = region.t + (y_ratio * region.h as f64) as i32;
 
-        sleep(Duration::from_millis(50));
+        sleep::ms(50);
+        if crate::cancel::requested() {
+            return Err(InputError::Cancelled);
+        }
 
         unsafe {
             SetCursorPos(screen_x, screen_y).ok();
-            sleep(Duration::from_millis(50));
+            sleep::ms(50);
 
+            // Once pressed, always release, even when a stop request cut the wait short
             let down = mouse_input(MOUSEEVENTF_LEFTDOWN);
-            SendInput(&[down], std::mem::size_of::<INPUT>() as i32);
-            sleep(Duration::from_millis(50));
+            if SendInput(&[down], std::mem::size_of::<INPUT>() as i32) == 0 {
+                return Err(InputError::Failed);
+            }
+            sleep::ms(50);
 
             let up = mouse_input(MOUSEEVENTF_LEFTUP);
             SendInput(&[up], std::mem::size_of::<INPUT>() as i32);
-            sleep(Duration::from_millis(50));
+            sleep::ms(50);
         }
+        Ok(())
     }
 
-    fn tap(&mut self, key: &str) {
-        sleep::jittered_ms(100, 0.3);
-
-        let (mods, main_key) = parse_key(key);
+    fn move_relative(&mut self, x_ratio: f64, y_ratio: f64) {
+        // Called once per path step, so reuse the cached region
+        if self.region.is_none() {
+            self.do_update();
+        }
+        let Some(region) = self.region else { return };
 
-        // Press modifiers
+        let screen_x = region.l + (x_ratio * region.w as f64) as i32;
+        let screen_y = region.t + (y_ratio * region.h as f64) as i32;
         unsafe {
-            for &m in &mods {
-                let input = key_input(m, false);
-                SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
-                sleep::jittered_ms(20, 0.3);
-            }
+            SetCursorPos(screen_x, screen_y).ok();
         }
+    }
 
-        // Press main key (VK or Unicode)
-        if let Some(vk) = vk_code(&main_key.to_lowercase()) {
-            self.send_vk(vk);
-        } else if main_key.len() == 1 {
-            // Fallback: treat single unknown char as unicode
-            let ch = main_key.chars().next().unwrap() as u16;
-            self.send_unicode_char(ch);
-        } else {
-            logger::warn_p("win32", &format!("unknown key: {}", main_key));
-        }
+    fn tap(&mut self, key: &str) -> Result<(), InputError> {
+        self.press(key, &mut default_pause)
+    }
 
-        // Release modifiers in reverse
-        unsafe {
-            for &m in mods.iter().rev() {
-                let input = key_input(m, true);
-                SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
-                sleep::jittered_ms(20, 0.3);
-            }
-        }
+    fn tap_timed(&mut self, key: &str, pause: &mut dyn FnMut(KeyPause) -> u64) -> Result<(), InputError> {
+        self.press(key, pause)
     }
 
-    fn type_text(&mut self, text: &str) {
+    fn type_text(&mut self, text: &str) -> Result<(), InputError> {
         for ch in text.chars() {
-            self.tap(&ch.to_string());
+            if crate::cancel::requested() {
+                return Err(InputError::Cancelled);
+            }
+            self.tap(&ch.to_string())?;
         }
+        Ok(())
     }
 
     fn capture(&mut self, rect: Option<CaptureRect>) -> Option<Capture> {
@@ -0,0 +1,321 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Instant;
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::platform::{Platform, WindowHandle};
+use crate::types::*;
+
+/// Pause after the last recorded step when the recording ends right after it
+const MIN_LAST_DELAY: f64 = 1.0;
+
+/// One recorded input, replayed by the generated bot
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepAction {
+    /// Window-relative ratios, as taken by `win:click`
+    Click(f64, f64),
+    Tap(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub action: StepAction,
+    /// Seconds until the next step (the bot's cooldown after this one)
+    pub delay: f64,
+}
+
+/// Captures the user's clicks and key presses in one target window.
+///
+/// Clicks outside the window are dropped. The platform hooks see keys for
+/// every application, so key presses only count after a click inside the
+/// window and until the next click outside it.
+pub struct Recorder {
+    pattern: String,
+    win: Box<dyn WindowHandle>,
+    rx: mpsc::Receiver<InputEvent>,
+    stop: Arc<AtomicBool>,
+    steps: Vec<Step>,
+    last_at: Option<Instant>,
+    focused: bool,
+}
+
+impl Recorder {
+    /// Start recording in window `window_id`, or in the first window
+    /// matching `pattern` if None.
+    pub fn start(platform: &dyn Platform, pattern: &str, window_id: Option<WindowId>) -> Result<Self> {
+        let windows = platform.get_instances(pattern);
+        let window_id = match window_id {
+            Some(id) if windows.iter().any(|(w, _)| *w == id) => id,
+            Some(_) => bail!("the window is gone"),
+            None => windows.first()
+                .ok_or_else(|| anyhow!("no window matches \"{}\"", pattern))?.0,
+        };
+        let win = platform.create_window(pattern, window_id);
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        platform.start_input_recorder(tx, Arc::clone(&stop));
+        Ok(Self::new(pattern, win, rx, stop))
+    }
+
+    fn new(pattern: &str, win: Box<dyn WindowHandle>, rx: mpsc::Receiver<InputEvent>, stop: Arc<AtomicBool>) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            win,
+            rx,
+            stop,
+            steps: Vec::new(),
+            last_at: None,
+            focused: false,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        self.win.title()
+    }
+
+    /// Number of steps recorded so far.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Take the inputs reported since the last call.
+    pub fn poll(&mut self) {
+        while let Ok(event) = self.rx.try_recv() {
+            self.record(event, Instant::now());
+        }
+    }
+
+    fn record(&mut self, event: InputEvent, at: Instant) {
+        let action = match event {
+            InputEvent::Click { x, y } => {
+                // The window may have moved since the last click
+                self.win.update();
+                let inside = self.win.region().filter(|r| {
+                    r.w > 0 && r.h > 0 && x >= r.l && x < r.l + r.w && y >= r.t && y < r.t + r.h
+                });
+                self.focused = inside.is_some();
+                let Some(r) = inside else { return };
+                StepAction::Click(
+                    (x - r.l) as f64 / r.w as f64,
+                    (y - r.t) as f64 / r.h as f64,
+                )
+            }
+            InputEvent::Key(key) if self.focused => StepAction::Tap(key),
+            InputEvent::Key(_) => return,
+        };
+        if let (Some(prev), Some(last)) = (self.steps.last_mut(), self.last_at) {
+            prev.delay = at.duration_since(last).as_secs_f64();
+        }
+        self.steps.push(Step { action, delay: 0.0 });
+        self.last_at = Some(at);
+    }
+
+    /// Stop the platform hooks and return what was recorded.
+    pub fn finish(mut self) -> Recording {
+        self.poll();
+        if let (Some(last), Some(at)) = (self.steps.last_mut(), self.last_at) {
+            last.delay = at.elapsed().as_secs_f64().max(MIN_LAST_DELAY);
+        }
+        Recording {
+            pattern: std::mem::take(&mut self.pattern),
+            title: self.win.title().to_string(),
+            steps: std::mem::take(&mut self.steps),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+    }
+}
+
+/// A finished recording, ready to be written out as a bot
+pub struct Recording {
+    pub pattern: String,
+    pub title: String,
+    pub steps: Vec<Step>,
+}
+
+impl Recording {
+    /// Source of a runnable bot that replays the steps in a loop, one per tick.
+    pub fn to_lua(&self) -> String {
+        let mut steps = String::new();
+        for step in &self.steps {
+            let line = match &step.action {
+                StepAction::Click(x, y) => format!("{{ \"click\", {:.3}, {:.3}, {:.2} }},", x, y, step.delay),
+                StepAction::Tap(key) => format!("{{ \"tap\", {:?}, {:.2} }},", key, step.delay),
+            };
+            steps.push_str("    ");
+            steps.push_str(&line);
+            steps.push('\n');
+        }
+        TEMPLATE
+            // The title goes in a comment, which any line break would end
+            .replace("{title}", &self.title.replace(|c: char| c.is_control(), " "))
+            .replace("{pattern}", &format!("{:?}", self.pattern))
+            .replace("{steps}", &steps)
+    }
+
+    /// Write `<bots_dir>/<name>/main.lua`, appending -2, -3, … to `name`
+    /// rather than overwriting an existing bot. Returns the script path.
+    pub fn save(&self, bots_dir: &Path, name: &str) -> Result<PathBuf> {
+        if self.steps.is_empty() {
+            bail!("nothing recorded");
+        }
+        let dir = (1..)
+            .map(|n| match n {
+                1 => bots_dir.join(name),
+                n => bots_dir.join(format!("{}-{}", name, n)),
+            })
+            .find(|d| !d.exists())
+            .unwrap();
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("main.lua");
+        std::fs::write(&path, self.to_lua())?;
+        Ok(path)
+    }
+}
+
+const TEMPLATE: &str = r#"-- Recorded with finger from "{title}"
+-- Each step is { "click", x_ratio, y_ratio, cooldown } or { "tap", key, cooldown };
+-- cooldowns are the pauses measured while recording.
+
+local win = nil
+local steps = {
+{steps}}
+local current = 1
+
+return {
+    window_pattern = {pattern},
+    description = "Recorded macro",
+
+    start = function(w)
+        win = w
+        current = 1
+    end,
+
+    tick = function()
+        local step = steps[current]
+        if step[1] == "click" then
+            win:click(step[2], step[3])
+        else
+            win:tap(step[2])
+        end
+        current = current % #steps + 1
+        return step[#step]
+    end,
+
+    get_status = function()
+        return string.format("step %d/%d", current, #steps)
+    end,
+
+    reset = function()
+        current = 1
+    end,
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::platform::stub::StubPlatform;
+
+    /// Recorder on a 1920x1080 stub window at the screen origin
+    fn recorder() -> Recorder {
+        let (_tx, rx) = mpsc::channel();
+        Recorder::new("Game", StubPlatform.create_window("Game", 1), rx, Arc::new(AtomicBool::new(false)))
+    }
+
+    fn actions(r: &Recorder) -> Vec<StepAction> {
+        r.steps.iter().map(|s| s.action.clone()).collect()
+    }
+
+    #[test]
+    fn clicks_outside_the_window_are_dropped() {
+        let mut r = recorder();
+        let t0 = Instant::now();
+        r.record(InputEvent::Click { x: 480, y: 270 }, t0);
+        r.record(InputEvent::Click { x: 1920, y: 10 }, t0);
+        r.record(InputEvent::Click { x: -5, y: 10 }, t0);
+        assert_eq!(actions(&r), [StepAction::Click(0.25, 0.25)]);
+    }
+
+    #[test]
+    fn keys_count_only_while_the_window_has_focus() {
+        let mut r = recorder();
+        let t0 = Instant::now();
+        r.record(InputEvent::Key("a".into()), t0);
+        r.record(InputEvent::Click { x: 0, y: 0 }, t0);
+        r.record(InputEvent::Key("b".into()), t0);
+        r.record(InputEvent::Click { x: 5000, y: 0 }, t0);
+        r.record(InputEvent::Key("c".into()), t0);
+        assert_eq!(actions(&r), [StepAction::Click(0.0, 0.0), StepAction::Tap("b".into())]);
+    }
+
+    #[test]
+    fn delays_are_the_pauses_between_steps() {
+        let mut r = recorder();
+        let t0 = Instant::now();
+        r.record(InputEvent::Click { x: 10, y: 10 }, t0);
+        r.record(InputEvent::Key("x".into()), t0 + Duration::from_millis(1500));
+        // Dropped input does not end the pause
+        r.record(InputEvent::Click { x: 9999, y: 10 }, t0 + Duration::from_millis(1800));
+        r.record(InputEvent::Click { x: 20, y: 20 }, t0 + Duration::from_millis(2000));
+        let recording = r.finish();
+        let delays: Vec<f64> = recording.steps.iter().map(|s| s.delay).collect();
+        assert_eq!(delays[..2], [1.5, 0.5]);
+        assert!(delays[2] >= MIN_LAST_DELAY);
+    }
+
+    #[test]
+    fn to_lua_escapes_strings() {
+        let recording = Recording {
+            pattern: r#"Game "2" \ [x]"#.into(),
+            title: "Game\r\n-- \"2\"".into(),
+            steps: vec![
+                Step { action: StepAction::Tap("\"".into()), delay: 0.25 },
+                Step { action: StepAction::Click(0.5, 0.125), delay: 1.0 },
+            ],
+        };
+        let lua = mlua::Lua::new();
+        let bot: mlua::Table = lua.load(recording.to_lua()).eval().unwrap();
+        assert_eq!(bot.get::<String>("window_pattern").unwrap(), recording.pattern);
+        // Replay both steps against a window that writes down what it gets
+        let replay = lua.load(r#"
+            local bot = ...
+            local log = {}
+            bot.start({
+                click = function(_, x, y) log[#log + 1] = x .. "," .. y end,
+                tap = function(_, key) log[#log + 1] = key end,
+            })
+            local first = bot.tick()
+            local second = bot.tick()
+            return log[1], first, log[2], second
+        "#).call::<(String, f64, String, f64)>(bot).unwrap();
+        assert_eq!(replay, ("\"".to_string(), 0.25, "0.5,0.125".to_string(), 1.0));
+    }
+
+    #[test]
+    fn save_never_overwrites_a_bot() {
+        let dir = std::env::temp_dir().join(format!("finger-rec-{}-save", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        let mut recording = Recording { pattern: "Game".into(), title: "Game".into(), steps: Vec::new() };
+        assert!(recording.save(&dir, "macro").is_err());
+        recording.steps.push(Step { action: StepAction::Tap("a".into()), delay: 1.0 });
+        let paths: Vec<PathBuf> = (0..3).map(|_| recording.save(&dir, "macro").unwrap()).collect();
+        let names: Vec<&str> = paths.iter()
+            .map(|p| p.parent().unwrap().file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["macro", "macro-2", "macro-3"]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
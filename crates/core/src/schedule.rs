@@ -0,0 +1,335 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::store::{self, Store};
+
+/// A bot's schedule as written under `schedules` in settings.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    /// Local time ranges the bot may run in, e.g. "22:00-07:00" or
+    /// "sat,sun 10:00-14:00"; empty means any time
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<String>,
+    /// Running time allowed per calendar day
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_minutes_per_day: Option<u64>,
+    /// tick() calls allowed per calendar day
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_ticks_per_day: Option<u64>,
+}
+
+/// One time range on a set of weekdays. A range that ends at or before its
+/// start runs past midnight into the next day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Window {
+    /// Bit 0 is Monday
+    days: u8,
+    /// Minutes since midnight
+    start: u32,
+    end: u32,
+}
+
+const ALL_DAYS: u8 = 0x7f;
+const DAY_NAMES: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+impl Window {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split_whitespace();
+        let (days, times) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(t), None, _) if t.contains(':') => (ALL_DAYS, t),
+            (Some(d), None, _) => (parse_days(d)?, "00:00-24:00"),
+            (Some(d), Some(t), None) => (parse_days(d)?, t),
+            _ => return Err(format!("bad time window \"{}\"", spec)),
+        };
+        let (start, end) = times.split_once('-')
+            .ok_or_else(|| format!("bad time range \"{}\", expected HH:MM-HH:MM", times))?;
+        let start = parse_clock(start)?;
+        let end = parse_clock(end)?;
+        if start == 24 * 60 {
+            return Err(format!("time range \"{}\" starts at 24:00", times));
+        }
+        Ok(Self { days, start, end })
+    }
+
+    fn on(&self, weekday: u32) -> bool {
+        self.days & (1 << weekday) != 0
+    }
+
+    fn contains(&self, weekday: u32, minute: u32) -> bool {
+        if self.start < self.end {
+            self.on(weekday) && minute >= self.start && minute < self.end
+        } else {
+            // Runs from `start` on its own day until `end` the day after
+            (self.on(weekday) && minute >= self.start) || (self.on((weekday + 6) % 7) && minute < self.end)
+        }
+    }
+}
+
+/// "mon-fri", "sat,sun", "daily" → weekday bitmask. Days are full names
+/// or their first three letters.
+fn parse_days(spec: &str) -> Result<u8, String> {
+    let day = |name: &str| {
+        let name = name.to_lowercase();
+        DAY_NAMES.iter()
+            .position(|d| name == *d || name == d[..3])
+            .ok_or_else(|| format!("unknown weekday \"{}\"", name))
+    };
+    if spec == "daily" {
+        return Ok(ALL_DAYS);
+    }
+    let mut days = 0u8;
+    for part in spec.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (day(from)?, day(to)?);
+                // mon-fri, or fri-mon wrapping over the weekend
+                let mut d = from;
+                loop {
+                    days |= 1 << d;
+                    if d == to { break; }
+                    d = (d + 1) % 7;
+                }
+            }
+            None => days |= 1 << day(part)?,
+        }
+    }
+    Ok(days)
+}
+
+/// "7:30" → 450
+fn parse_clock(spec: &str) -> Result<u32, String> {
+    let bad = || format!("bad time \"{}\", expected HH:MM", spec);
+    let (h, m) = spec.split_once(':').ok_or_else(bad)?;
+    let h: u32 = h.parse().map_err(|_| bad())?;
+    let m: u32 = m.parse().map_err(|_| bad())?;
+    if m >= 60 || h > 24 || (h == 24 && m > 0) {
+        return Err(bad());
+    }
+    Ok(h * 60 + m)
+}
+
+/// When a bot may run and how much per day
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    windows: Vec<Window>,
+    max_runtime: Option<Duration>,
+    max_ticks: Option<u64>,
+}
+
+/// Why a scheduled bot is held back, and when it may start again
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hold {
+    pub reason: &'static str,
+    /// Local time of the next start; None if the schedule never opens
+    pub until: Option<NaiveDateTime>,
+}
+
+impl Schedule {
+    pub fn parse(config: &ScheduleConfig) -> Result<Self, String> {
+        Ok(Self {
+            windows: config.windows.iter()
+                .map(|w| Window::parse(w))
+                .collect::<Result<_, _>>()?,
+            max_runtime: config.max_minutes_per_day.map(|m| Duration::from_secs(m * 60)),
+            max_ticks: config.max_ticks_per_day,
+        })
+    }
+
+    /// Whether anything is counted in the bot's daily usage.
+    pub fn has_quota(&self) -> bool {
+        self.max_runtime.is_some() || self.max_ticks.is_some()
+    }
+
+    fn is_open(&self, at: NaiveDateTime) -> bool {
+        let (weekday, minute) = (at.weekday().num_days_from_monday(), minute_of(at));
+        self.windows.is_empty() || self.windows.iter().any(|w| w.contains(weekday, minute))
+    }
+
+    /// First moment from `at` on that falls in a window.
+    fn next_open(&self, at: NaiveDateTime) -> Option<NaiveDateTime> {
+        if self.is_open(at) {
+            return Some(at);
+        }
+        (0..=7)
+            .flat_map(|offset| {
+                let date = at.date() + TimeDelta::days(offset);
+                let weekday = date.weekday().num_days_from_monday();
+                self.windows.iter()
+                    .filter(move |w| w.on(weekday))
+                    .map(move |w| date.and_time(NaiveTime::MIN) + TimeDelta::minutes(w.start as i64))
+            })
+            .filter(|start| *start > at)
+            .min()
+    }
+
+    /// Why the bot must not run at `now` after using `usage` today, or None
+    /// if it may.
+    pub fn hold(&self, now: NaiveDateTime, usage: &Usage) -> Option<Hold> {
+        let today = now.date();
+        let runtime_used = self.max_runtime.is_some_and(|max| usage.runtime(today) >= max);
+        let ticks_used = self.max_ticks.is_some_and(|max| usage.ticks(today) >= max);
+        if runtime_used || ticks_used {
+            let midnight = (today + TimeDelta::days(1)).and_time(NaiveTime::MIN);
+            return Some(Hold {
+                reason: if runtime_used { "daily runtime used up" } else { "daily ticks used up" },
+                until: self.next_open(midnight),
+            });
+        }
+        if !self.is_open(now) {
+            return Some(Hold { reason: "outside its schedule", until: self.next_open(now) });
+        }
+        None
+    }
+}
+
+fn minute_of(at: NaiveDateTime) -> u32 {
+    at.hour() * 60 + at.minute()
+}
+
+impl Hold {
+    /// e.g. "outside its schedule, next start tomorrow 22:00"
+    pub fn describe(&self) -> String {
+        let now = Local::now().naive_local();
+        match self.until {
+            Some(at) => format!("{}, next start {}", self.reason, describe_start(at, now)),
+            None => format!("{}, no start scheduled", self.reason),
+        }
+    }
+}
+
+/// "07:00", "tomorrow 07:00" or "Sat 10:00", relative to `now`.
+fn describe_start(at: NaiveDateTime, now: NaiveDateTime) -> String {
+    match (at.date() - now.date()).num_days() {
+        0 => at.format("%H:%M").to_string(),
+        1 => at.format("tomorrow %H:%M").to_string(),
+        _ => at.format("%a %H:%M").to_string(),
+    }
+}
+
+/// Running time and ticks one bot used today, kept in its `<bot>/usage`
+/// store so restarting finger does not reset the daily quota.
+pub struct Usage {
+    store: Arc<Mutex<Store>>,
+    date: NaiveDate,
+    runtime: Duration,
+    ticks: u64,
+}
+
+impl Usage {
+    pub fn open(bot: &str) -> Self {
+        let store = store::open(&format!("{}/usage", bot));
+        let (date, runtime, ticks) = {
+            let s = store.lock().unwrap();
+            (
+                s.get("date").and_then(|v| v.as_str()).and_then(|d| d.parse().ok()),
+                s.get("runtime_s").and_then(|v| v.as_f64()).unwrap_or(0.0),
+                s.get("ticks").and_then(|v| v.as_u64()).unwrap_or(0),
+            )
+        };
+        Self {
+            store,
+            date: date.unwrap_or_default(),
+            runtime: Duration::from_secs_f64(runtime.max(0.0)),
+            ticks,
+        }
+    }
+
+    fn runtime(&self, today: NaiveDate) -> Duration {
+        if self.date == today { self.runtime } else { Duration::ZERO }
+    }
+
+    fn ticks(&self, today: NaiveDate) -> u64 {
+        if self.date == today { self.ticks } else { 0 }
+    }
+
+    /// Count running time and ticks towards `today`, starting over on a new day.
+    pub fn add(&mut self, today: NaiveDate, runtime: Duration, ticks: u64) {
+        if self.date != today {
+            self.date = today;
+            self.runtime = Duration::ZERO;
+            self.ticks = 0;
+        }
+        self.runtime += runtime;
+        self.ticks += ticks;
+        let mut s = self.store.lock().unwrap();
+        s.set("date", json!(self.date.to_string()));
+        s.set("runtime_s", json!(self.runtime.as_secs_f64()));
+        s.set("ticks", json!(self.ticks));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn schedule(windows: &[&str]) -> Schedule {
+        Schedule::parse(&ScheduleConfig {
+            windows: windows.iter().map(|w| w.to_string()).collect(),
+            ..Default::default()
+        }).unwrap()
+    }
+
+    #[test]
+    fn parses_day_names() {
+        assert_eq!(parse_days("mon"), Ok(0b1));
+        assert_eq!(parse_days("Sunday"), Ok(0b100_0000));
+        assert_eq!(parse_days("mon-fri"), Ok(0b1_1111));
+        assert_eq!(parse_days("sat,sun"), Ok(0b110_0000));
+        assert_eq!(parse_days("fri-mon"), Ok(0b111_0001));
+        assert_eq!(parse_days("daily"), Ok(ALL_DAYS));
+        for bad in ["saturn", "mo", "monda", "tues", "", "mon-"] {
+            assert!(parse_days(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn parses_windows() {
+        assert_eq!(Window::parse("22:00-07:00"), Ok(Window { days: ALL_DAYS, start: 22 * 60, end: 7 * 60 }));
+        assert_eq!(Window::parse("sat,sun 10:00-14:30"), Ok(Window { days: 0b110_0000, start: 600, end: 870 }));
+        assert_eq!(Window::parse("wed"), Ok(Window { days: 0b100, start: 0, end: 24 * 60 }));
+        for bad in ["24:00-01:00", "10:00", "9-17", "10:60-11:00", "mon 10:00-11:00 x", "saturn 10:00-11:00"] {
+            assert!(Window::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn window_runs_past_midnight() {
+        // Friday night into Saturday morning
+        let s = schedule(&["fri 22:00-02:00"]);
+        assert!(s.is_open(at("2026-10-16", "23:30")));
+        assert!(s.is_open(at("2026-10-17", "01:59")));
+        assert!(!s.is_open(at("2026-10-17", "02:00")));
+        assert!(!s.is_open(at("2026-10-16", "01:00")));
+        assert!(!s.is_open(at("2026-10-17", "23:00")));
+    }
+
+    #[test]
+    fn next_open_crosses_the_week() {
+        // Sunday evening, next window is Monday morning
+        let s = schedule(&["mon 09:00-10:00"]);
+        assert_eq!(s.next_open(at("2026-10-18", "20:00")), Some(at("2026-10-19", "09:00")));
+        // Just after this week's window closed, the next is seven days out
+        assert_eq!(s.next_open(at("2026-10-19", "10:00")), Some(at("2026-10-26", "09:00")));
+        assert_eq!(s.next_open(at("2026-10-19", "09:30")), Some(at("2026-10-19", "09:30")));
+    }
+
+    #[test]
+    fn quota_holds_until_the_next_day() {
+        let s = Schedule::parse(&ScheduleConfig { max_ticks_per_day: Some(2), ..Default::default() }).unwrap();
+        let mut usage = Usage::open("schedule-test");
+        let now = at("2026-10-18", "12:00");
+        usage.add(now.date(), Duration::from_secs(1), 2);
+        let hold = s.hold(now, &usage).unwrap();
+        assert_eq!(hold, Hold { reason: "daily ticks used up", until: Some(at("2026-10-19", "00:00")) });
+        assert_eq!(s.hold(at("2026-10-19", "00:01"), &usage), None);
+    }
+}
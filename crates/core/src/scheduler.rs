@@ -0,0 +1,201 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Head start per priority level when several instances are due: a bot with
+/// priority 2 runs before one with priority 0 unless that one has been
+/// waiting 2 seconds longer
+pub const PRIORITY_STEP: Duration = Duration::from_secs(1);
+
+struct Slot {
+    priority: i32,
+    /// Matches the live heap entry; older entries for the id are stale
+    seq: u64,
+}
+
+/// Deadline queue of bot instances.
+///
+/// Instances wait in a min-heap by due time, so the orchestrator can sleep
+/// until exactly the next deadline. When several are due, the one that has
+/// waited longest, plus PRIORITY_STEP per priority level, runs first: a busy
+/// high-priority bot delays the others by a bounded amount but cannot
+/// starve them.
+#[derive(Default)]
+pub struct Scheduler {
+    slots: HashMap<String, Slot>,
+    heap: BinaryHeap<Reverse<(Instant, u64, String)>>,
+    next_seq: u64,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `id` to run at `due`, replacing its current deadline.
+    pub fn schedule(&mut self, id: &str, due: Instant) {
+        let priority = self.slots.get(id).map_or(0, |s| s.priority);
+        self.insert(id, due, priority);
+    }
+
+    /// Queue `id` to run now if it is not queued yet; otherwise only update
+    /// its priority.
+    pub fn admit(&mut self, id: &str, priority: i32) {
+        match self.slots.get_mut(id) {
+            Some(slot) => slot.priority = priority,
+            None => self.insert(id, Instant::now(), priority),
+        }
+    }
+
+    fn insert(&mut self, id: &str, due: Instant, priority: i32) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.slots.insert(id.to_string(), Slot { priority, seq });
+        self.heap.push(Reverse((due, seq, id.to_string())));
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.slots.remove(id);
+    }
+
+    /// Drop every instance `keep` rejects.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.slots.retain(|id, _| keep(id));
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.heap.clear();
+    }
+
+    fn is_live(&self, seq: u64, id: &str) -> bool {
+        self.slots.get(id).is_some_and(|s| s.seq == seq)
+    }
+
+    /// Earliest deadline of any queued instance.
+    pub fn next_due(&mut self) -> Option<Instant> {
+        while let Some(Reverse((due, seq, id))) = self.heap.peek() {
+            if self.is_live(*seq, id) {
+                return Some(*due);
+            }
+            self.heap.pop();
+        }
+        None
+    }
+
+    /// Take the due instance that should run first, with how late it is.
+    /// It leaves the queue until scheduled again.
+    pub fn pop_due(&mut self, now: Instant) -> Option<(String, Duration)> {
+        // Due entries sit at the top of the heap; lift them all out to rank
+        let mut due = Vec::new();
+        while let Some(Reverse((at, _, _))) = self.heap.peek() {
+            if *at > now {
+                break;
+            }
+            let Reverse((at, seq, id)) = self.heap.pop().unwrap();
+            if self.is_live(seq, &id) {
+                due.push((at, seq, id));
+            }
+        }
+        let score = |at: Instant, id: &str| {
+            let priority = self.slots.get(id).map_or(0, |s| s.priority);
+            (now - at).as_secs_f64() + priority as f64 * PRIORITY_STEP.as_secs_f64()
+        };
+        // Highest score wins; on a tie the one queued first
+        let best = due.iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                score(a.0, &a.2).total_cmp(&score(b.0, &b.2)).then(b.1.cmp(&a.1))
+            })
+            .map(|(i, _)| i)?;
+        let (at, _, id) = due.swap_remove(best);
+        for entry in due {
+            self.heap.push(Reverse(entry));
+        }
+        self.slots.remove(&id);
+        Some((id, now - at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(s: &mut Scheduler, now: Instant) -> Vec<String> {
+        std::iter::from_fn(|| s.pop_due(now).map(|(id, _)| id)).collect()
+    }
+
+    #[test]
+    fn longest_waiting_runs_first() {
+        let t0 = Instant::now();
+        let mut s = Scheduler::new();
+        s.schedule("b", t0 + Duration::from_millis(200));
+        s.schedule("a", t0 + Duration::from_millis(100));
+        s.schedule("c", t0 + Duration::from_secs(10));
+        assert_eq!(s.next_due(), Some(t0 + Duration::from_millis(100)));
+        let now = t0 + Duration::from_secs(1);
+        assert_eq!(s.pop_due(now), Some(("a".to_string(), Duration::from_millis(900))));
+        assert_eq!(drain(&mut s, now), ["b"]);
+        assert_eq!(s.next_due(), Some(t0 + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn priority_is_a_bounded_head_start() {
+        let t0 = Instant::now();
+        let mut s = Scheduler::new();
+        s.admit("low", 0);
+        s.admit("high", 2);
+        s.schedule("low", t0);
+        s.schedule("high", t0 + Duration::from_secs(1));
+        // high has waited 1s less but is 2 levels up
+        assert_eq!(drain(&mut s, t0 + Duration::from_secs(2)), ["high", "low"]);
+
+        s.schedule("low", t0);
+        s.schedule("high", t0 + Duration::from_secs(3));
+        // low has now waited 3s longer, more than the head start
+        assert_eq!(drain(&mut s, t0 + Duration::from_secs(4)), ["low", "high"]);
+    }
+
+    #[test]
+    fn rescheduling_replaces_the_deadline() {
+        let t0 = Instant::now();
+        let mut s = Scheduler::new();
+        s.schedule("a", t0);
+        s.schedule("a", t0 + Duration::from_secs(5));
+        assert_eq!(s.next_due(), Some(t0 + Duration::from_secs(5)));
+        assert_eq!(s.pop_due(t0 + Duration::from_secs(1)), None);
+        assert_eq!(drain(&mut s, t0 + Duration::from_secs(5)), ["a"]);
+    }
+
+    #[test]
+    fn popped_instances_are_admitted_again() {
+        let t0 = Instant::now();
+        let mut s = Scheduler::new();
+        s.admit("a", 1);
+        assert_eq!(drain(&mut s, Instant::now()), ["a"]);
+        // While it runs it is out of the queue
+        assert_eq!(s.next_due(), None);
+        s.admit("a", 1);
+        assert!(s.next_due().is_some_and(|at| at >= t0));
+        // Admitting a queued instance keeps its deadline
+        s.schedule("a", t0 + Duration::from_secs(60));
+        s.admit("a", 3);
+        assert_eq!(s.next_due(), Some(t0 + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn removed_instances_never_run() {
+        let t0 = Instant::now();
+        let mut s = Scheduler::new();
+        for id in ["a", "b", "c"] {
+            s.schedule(id, t0);
+        }
+        s.remove("a");
+        s.retain(|id| id != "b");
+        assert_eq!(drain(&mut s, t0), ["c"]);
+        s.schedule("a", t0);
+        s.clear();
+        assert_eq!(s.next_due(), None);
+        assert_eq!(s.pop_due(t0), None);
+    }
+}
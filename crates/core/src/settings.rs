@@ -1,18 +1,69 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 
+use crate::humanize::HumanizeConfig;
+use crate::logger;
+use crate::options::OptionValues;
+use crate::schedule::ScheduleConfig;
+use crate::types::BotEntry;
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Settings {
     pub enabled_bots: Vec<String>,
+    /// Keys (`<bot>#<slot>`) of instances switched off in enabled bots
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_instances: Vec<String>,
+    /// Option overrides per bot name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, OptionValues>,
+    /// Global input humanization, overridable per bot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub humanize: Option<HumanizeConfig>,
+    /// Time windows and daily quotas per bot name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub schedules: BTreeMap<String, ScheduleConfig>,
 }
 
 impl Settings {
     pub fn load(path: &Path) -> Self {
-        std::fs::read_to_string(path)
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default()
+        Self::read(path).unwrap_or_else(|e| {
+            logger::warn(&format!("ignoring {}: {}", path.display(), e));
+            Self::default()
+        })
+    }
+
+    /// Like `load`, but a file that exists and cannot be parsed is an error.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(s) => serde_json::from_str(&s).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Take enabled bots and instances and option overrides from `entries`,
+    /// keeping hand-edited fields such as `humanize` as they are. Bots not in
+    /// `entries`, e.g. because they failed to load, keep their settings.
+    pub fn update(&mut self, entries: &[BotEntry]) {
+        let loaded = |name: &str| entries.iter().any(|e| e.name == name);
+        self.enabled_bots.retain(|name| !loaded(name));
+        self.enabled_bots.extend(entries.iter()
+            .filter(|e| e.enabled)
+            .map(|e| e.name.clone()));
+        // Keys of windows that are not open right now keep their setting
+        self.disabled_instances.retain(|key| {
+            !entries.iter().flat_map(|e| &e.instances).any(|i| i.key == *key)
+        });
+        self.disabled_instances.extend(entries.iter()
+            .flat_map(|e| &e.instances)
+            .filter(|i| !i.enabled)
+            .map(|i| i.key.clone()));
+        self.options.retain(|name, _| !loaded(name));
+        self.options.extend(entries.iter()
+            .filter(|e| !e.option_values.is_empty())
+            .map(|e| (e.name.clone(), e.option_values.clone())));
     }
 
     pub fn save(&self, path: &Path) {
@@ -21,3 +72,86 @@ impl Settings {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::types::{Instance, RestartPolicy};
+
+    fn entry(name: &str, windows: Vec<(u64, String)>) -> BotEntry {
+        BotEntry {
+            name: name.to_string(),
+            window_pattern: String::new(),
+            description: String::new(),
+            enabled: true,
+            instances: Instance::for_windows(name, windows),
+            error: None,
+            script_path: Default::default(),
+            options: Vec::new(),
+            option_values: OptionValues::default(),
+            restart: RestartPolicy::default(),
+            actions: Vec::new(),
+            priority: 0,
+            stuck_after: Duration::from_secs(60),
+            schedule: None,
+            hold: None,
+        }
+    }
+
+    #[test]
+    fn bots_that_did_not_load_keep_their_settings() {
+        let mut settings: Settings = serde_json::from_str(r#"{
+            "enabled_bots": ["farm", "broken"],
+            "options": { "farm": { "bot": { "speed": 2 } }, "broken": { "bot": { "speed": 3 } } }
+        }"#).unwrap();
+        let mut entries = vec![entry("farm", Vec::new())];
+        entries[0].enabled = false;
+        settings.update(&entries);
+        assert_eq!(settings.enabled_bots, vec!["broken"]);
+        assert_eq!(settings.options.keys().collect::<Vec<_>>(), ["broken"]);
+
+        entries[0].enabled = true;
+        entries[0].option_values = serde_json::from_str(r#"{ "bot": { "speed": 5 } }"#).unwrap();
+        settings.update(&entries);
+        assert_eq!(settings.enabled_bots, vec!["broken", "farm"]);
+        assert_eq!(serde_json::to_value(&settings.options).unwrap(), serde_json::json!({
+            "broken": { "bot": { "speed": 3 } }, "farm": { "bot": { "speed": 5 } },
+        }));
+    }
+
+    #[test]
+    fn read_reports_broken_files() {
+        let dir = std::env::temp_dir().join(format!("finger-settings-{}-read", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+        assert!(Settings::read(&path).unwrap().enabled_bots.is_empty());
+        std::fs::write(&path, r#"{ "enabled_bots": ["farm"] }"#).unwrap();
+        assert_eq!(Settings::read(&path).unwrap().enabled_bots, vec!["farm"]);
+        std::fs::write(&path, r#"{ "enabled_bots": ["farm"], }"#).unwrap();
+        assert!(Settings::read(&path).is_err());
+        assert!(Settings::load(&path).enabled_bots.is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn disabled_instances_follow_keys() {
+        let mut entries = vec![entry("farm", vec![(20, "b".into()), (10, "a".into())])];
+        entries[0].instances[1].enabled = false;
+        let mut settings = Settings::default();
+        settings.update(&entries);
+        assert_eq!(settings.enabled_bots, vec!["farm"]);
+        assert_eq!(settings.disabled_instances, vec!["farm#2"]);
+
+        // farm#2's window is closed: its setting is kept for when it returns
+        entries[0].instances.truncate(1);
+        settings.update(&entries);
+        assert_eq!(settings.disabled_instances, vec!["farm#2"]);
+
+        // Back on once the window is open again and switched on
+        entries[0] = entry("farm", vec![(10, "a".into()), (20, "b".into())]);
+        settings.update(&entries);
+        assert!(settings.disabled_instances.is_empty());
+    }
+}
@@ -1,18 +1,46 @@
 use rand::Rng;
+use std::sync::OnceLock;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-/// Sleep for exact milliseconds (no jitter).
+use crate::cancel;
+
+/// How often a sleep checks for a stop request
+const CANCEL_POLL: Duration = Duration::from_millis(10);
+
+/// Monotonic seconds since the clock was first read (backs `F.now`).
+pub fn now() -> f64 {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_secs_f64()
+}
+
+/// Sleep for `d`, returning early once a stop is requested.
+fn interruptible(d: Duration) {
+    let end = Instant::now() + d;
+    while !cancel::requested() {
+        let now = Instant::now();
+        if now >= end {
+            break;
+        }
+        thread::sleep((end - now).min(CANCEL_POLL));
+    }
+}
+
+/// Sleep for exact milliseconds (no jitter). Returns early on a stop request.
 pub fn ms(ms: u64) {
-    thread::sleep(Duration::from_millis(ms));
+    interruptible(Duration::from_millis(ms));
 }
 
-/// Sleep for `base_ms` with +/-`percent` random jitter.
-pub fn jittered_ms(base_ms: u64, percent: f64) {
+/// `base_ms` with +/-`percent` random jitter, at least 1.
+pub fn jitter(base_ms: u64, percent: f64) -> u64 {
     let base = base_ms as f64;
     let pct = if percent.is_finite() && percent >= 0.0 { percent } else { 0.3 };
     let jitter = base * pct;
     let actual = base + rand::thread_rng().gen_range(-jitter..jitter);
-    let actual_ms = actual.max(1.0).round() as u64;
-    thread::sleep(Duration::from_millis(actual_ms));
+    actual.max(1.0).round() as u64
+}
+
+/// Sleep for `base_ms` with +/-`percent` random jitter. Returns early on a stop request.
+pub fn jittered_ms(base_ms: u64, percent: f64) {
+    interruptible(Duration::from_millis(jitter(base_ms, percent)));
 }
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde_json::{Map, Value};
+
+use crate::logger;
+
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+static STORES: OnceLock<Mutex<HashMap<String, Arc<Mutex<Store>>>>> = OnceLock::new();
+
+/// How long a change may sit in memory before `flush_due` writes it, so a
+/// crash loses little while a bot setting a value every tick does not
+/// rewrite its file every tick
+const FLUSH_DELAY: Duration = Duration::from_secs(2);
+
+/// Persistent key/value store backed by one JSON file.
+/// Values are cached in memory and written on `flush` or `flush_due`.
+pub struct Store {
+    path: Option<PathBuf>,
+    data: Map<String, Value>,
+    /// When the oldest change not yet on disk was made
+    dirty_since: Option<Instant>,
+    /// The file could not be read nor moved aside; writing would lose it
+    broken: bool,
+}
+
+/// Set the root directory for store files. Without it stores are memory-only.
+pub fn init(data_dir: &Path) {
+    DATA_DIR.set(data_dir.to_path_buf()).ok();
+}
+
+/// Open (or reuse) the store for `scope`, e.g. `wow-rally-hk/store`.
+/// All VMs asking for the same scope share one cached instance.
+pub fn open(scope: &str) -> Arc<Mutex<Store>> {
+    let stores = STORES.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut stores = stores.lock().unwrap();
+    let store = stores.entry(scope.to_string()).or_insert_with(|| {
+        let path = DATA_DIR.get().map(|d| d.join(format!("{}.json", scope)));
+        Arc::new(Mutex::new(Store::load(path)))
+    });
+    Arc::clone(store)
+}
+
+/// Write every dirty store to disk. Called at shutdown.
+pub fn flush_all() {
+    let Some(stores) = STORES.get() else { return };
+    for store in stores.lock().unwrap().values() {
+        store.lock().unwrap().flush();
+    }
+}
+
+/// Write the stores whose changes are older than `FLUSH_DELAY`. Called
+/// from the orchestrator loop, so a crash only loses the last moments.
+pub fn flush_due() {
+    let Some(stores) = STORES.get() else { return };
+    let now = Instant::now();
+    for store in stores.lock().unwrap().values() {
+        store.lock().unwrap().flush_if_due(now);
+    }
+}
+
+/// Contents of a store file; a missing file is an empty store.
+fn read(path: &Path) -> Result<Map<String, Value>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(s) => serde_json::from_str(&s).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Map::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+impl Store {
+    /// Read the store at `path`. A file that cannot be read or parsed is
+    /// moved aside to `*.bak` and the store starts empty; if even that
+    /// fails the store is never written, so the file is not overwritten.
+    fn load(path: Option<PathBuf>) -> Self {
+        let mut store = Self { path, data: Map::new(), dirty_since: None, broken: false };
+        let Some(path) = &store.path else { return store };
+        match read(path) {
+            Ok(data) => store.data = data,
+            Err(e) => {
+                let bak = path.with_extension("json.bak");
+                match std::fs::rename(path, &bak) {
+                    Ok(()) => logger::error(&format!(
+                        "cannot read {}: {}; moved it to {} and starting empty",
+                        path.display(), e, bak.display()
+                    )),
+                    Err(mv) => {
+                        logger::error(&format!(
+                            "cannot read {}: {}; cannot move it aside ({}), so changes will not be saved",
+                            path.display(), e, mv
+                        ));
+                        store.broken = true;
+                    }
+                }
+            }
+        }
+        store
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.data.get(key)
+    }
+
+    pub fn set(&mut self, key: &str, value: Value) {
+        self.data.insert(key.to_string(), value);
+        self.touch();
+    }
+
+    pub fn delete(&mut self, key: &str) {
+        if self.data.remove(key).is_some() {
+            self.touch();
+        }
+    }
+
+    fn touch(&mut self) {
+        self.dirty_since.get_or_insert_with(Instant::now);
+    }
+
+    fn flush_if_due(&mut self, now: Instant) {
+        if self.dirty_since.is_some_and(|at| now.duration_since(at) >= FLUSH_DELAY) {
+            self.flush();
+        }
+    }
+
+    /// Write to disk if anything changed. Writes a temp file and renames it
+    /// over the old one so a crash never leaves a half-written store.
+    pub fn flush(&mut self) {
+        if self.dirty_since.is_none() || self.broken {
+            return;
+        }
+        let Some(path) = &self.path else { return };
+        let result = (|| -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let tmp = path.with_extension("json.tmp");
+            let json = serde_json::to_string_pretty(&self.data)?;
+            std::fs::write(&tmp, json)?;
+            std::fs::rename(&tmp, path)
+        })();
+        match result {
+            Ok(()) => self.dirty_since = None,
+            Err(e) => logger::error(&format!("failed to write {}: {}", path.display(), e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// A fresh directory for one test's files
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("finger-store-{}-{}", std::process::id(), name));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn values_survive_a_reload() {
+        let path = temp_dir("reload").join("farm.json");
+        let mut store = Store::load(Some(path.clone()));
+        store.set("gold", json!(12));
+        store.set("tmp", json!("x"));
+        store.delete("tmp");
+        store.flush();
+
+        let store = Store::load(Some(path));
+        assert_eq!(store.get("gold"), Some(&json!(12)));
+        assert_eq!(store.get("tmp"), None);
+    }
+
+    #[test]
+    fn corrupt_file_is_moved_aside() {
+        let path = temp_dir("corrupt").join("farm.json");
+        std::fs::write(&path, "{ not json").unwrap();
+        let mut store = Store::load(Some(path.clone()));
+        assert_eq!(store.get("gold"), None);
+        assert_eq!(std::fs::read_to_string(path.with_extension("json.bak")).unwrap(), "{ not json");
+
+        store.set("gold", json!(1));
+        store.flush();
+        assert_eq!(read(&path).unwrap().get("gold"), Some(&json!(1)));
+    }
+
+    #[test]
+    fn unreadable_file_is_never_overwritten() {
+        // A directory where the file should be cannot be read, nor renamed
+        // over an existing *.bak directory
+        let dir = temp_dir("unreadable");
+        let path = dir.join("farm.json");
+        std::fs::create_dir_all(path.join("x")).unwrap();
+        std::fs::create_dir_all(path.with_extension("json.bak").join("y")).unwrap();
+        let mut store = Store::load(Some(path.clone()));
+        assert!(store.broken);
+        store.set("gold", json!(1));
+        store.flush();
+        assert!(path.is_dir());
+    }
+
+    #[test]
+    fn changes_are_written_after_the_delay() {
+        let path = temp_dir("due").join("farm.json");
+        let mut store = Store::load(Some(path.clone()));
+        store.set("gold", json!(1));
+        let start = Instant::now();
+        store.flush_if_due(start);
+        assert!(!path.exists());
+
+        store.flush_if_due(start + FLUSH_DELAY);
+        assert_eq!(read(&path).unwrap().get("gold"), Some(&json!(1)));
+        assert!(store.dirty_since.is_none());
+    }
+}
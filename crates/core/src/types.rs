@@ -1,3 +1,8 @@
+use std::time::{Duration, Instant};
+
+use crate::options::{OptionSpec, OptionValues};
+use crate::schedule::{Hold, Schedule};
+
 /// Window identifier (CGWindowID on macOS, HWND on Windows)
 pub type WindowId = u64;
 
@@ -32,6 +37,110 @@ pub struct Capture {
     pub bytes_per_row: u32,
 }
 
+/// A user input seen by the macro recorder, in screen coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    Click { x: i32, y: i32 },
+    /// Key in `win:tap` syntax, e.g. "space" or "ctrl+a"
+    Key(String),
+}
+
+/// Why an input call did not reach its window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputError {
+    /// The window no longer exists
+    Closed,
+    /// Another window has the foreground
+    Unfocused,
+    /// Called outside a tick, while the window is not activated
+    Inactive,
+    /// A stop request came in before the input was sent
+    Cancelled,
+    UnknownKey,
+    /// The OS refused to deliver the input
+    Failed,
+}
+
+impl InputError {
+    /// Name handed to Lua as the second return value of win:click etc.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InputError::Closed => "closed",
+            InputError::Unfocused => "unfocused",
+            InputError::Inactive => "inactive",
+            InputError::Cancelled => "cancelled",
+            InputError::UnknownKey => "unknown_key",
+            InputError::Failed => "failed",
+        }
+    }
+}
+
+impl std::fmt::Display for InputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What happens to an instance that hits a runtime error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Disable the bot on the first error
+    Never,
+    /// Recreate the failed instance with backoff; disable the bot once
+    /// `max_retries` restarts in a row have failed
+    OnError { max_retries: u32 },
+    /// Recreate the failed instance with backoff, forever
+    Always,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::OnError { max_retries: 3 }
+    }
+}
+
+impl RestartPolicy {
+    /// Parse the bot table's `restart` string; `max_retries` only applies to on-error.
+    pub fn parse(name: &str, max_retries: Option<u32>) -> Option<Self> {
+        match name {
+            "never" => Some(RestartPolicy::Never),
+            "on-error" => Some(RestartPolicy::OnError {
+                max_retries: max_retries.unwrap_or(3),
+            }),
+            "always" => Some(RestartPolicy::Always),
+            _ => None,
+        }
+    }
+
+    /// Whether an instance that has already been restarted `retries` times
+    /// may be restarted again.
+    pub fn allows(&self, retries: u32) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnError { max_retries } => retries < *max_retries,
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
+/// A manual command exported in a bot's `actions` table
+#[derive(Debug, Clone)]
+pub struct BotAction {
+    pub name: String,
+    pub label: String,
+}
+
+/// Metadata read from a bot's returned table during discovery
+pub struct BotMeta {
+    pub window_pattern: String,
+    pub description: String,
+    pub options: Vec<OptionSpec>,
+    pub restart: RestartPolicy,
+    pub actions: Vec<BotAction>,
+    pub priority: i32,
+    pub stuck_after: Duration,
+}
+
 /// One discovered bot script and its runtime state
 pub struct BotEntry {
     pub name: String,
@@ -41,27 +150,140 @@ pub struct BotEntry {
     pub instances: Vec<Instance>,
     pub error: Option<String>,
     pub script_path: std::path::PathBuf,
+    pub options: Vec<OptionSpec>,
+    pub option_values: OptionValues,
+    pub restart: RestartPolicy,
+    pub actions: Vec<BotAction>,
+    /// Scheduling priority; higher runs first when several instances are due
+    pub priority: i32,
+    /// How long one step of a run may take before the instance shows as stuck
+    pub stuck_after: Duration,
+    /// Time windows and daily quotas from settings
+    pub schedule: Option<Schedule>,
+    /// Set while the schedule keeps the bot from running
+    pub hold: Option<Hold>,
+}
+
+/// Severity badge of a structured status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusLevel {
+    Ok,
+    Warn,
+    Error,
+}
+
+impl StatusLevel {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ok" => Some(StatusLevel::Ok),
+            "warn" => Some(StatusLevel::Warn),
+            "error" => Some(StatusLevel::Error),
+            _ => None,
+        }
+    }
+}
+
+/// What get_status() reported: a plain string fills only `text`;
+/// a table can add a progress gauge, key/value fields and a badge.
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    pub text: String,
+    /// Fraction done, 0.0..=1.0
+    pub progress: Option<f64>,
+    /// Shown in key order
+    pub fields: Vec<(String, String)>,
+    pub level: Option<StatusLevel>,
+}
+
+impl Status {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self { text: text.into(), ..Self::default() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.progress.is_none() && self.fields.is_empty() && self.level.is_none()
+    }
 }
 
 /// One bot instance bound to a specific window
 pub struct Instance {
     pub id: String,
+    /// Stable key `<bot>#<slot>` used to persist per-instance settings.
+    /// Unlike `id` it survives window ids changing across game restarts.
+    pub key: String,
+    pub slot: usize,
     pub window_id: WindowId,
     pub window_title: String,
-    pub status: String,
+    /// Per-window switch under the bot's own; persisted by `key`
+    pub enabled: bool,
+    pub status: Status,
     pub error: Option<String>,
+    /// Restarts since the instance last ran cleanly
+    pub retries: u32,
+    pub last_error_at: Option<Instant>,
+    /// How long after its due time the instance last got to run
+    pub lateness: Duration,
+    /// Since when and in which step the running pass has been stuck
+    pub stuck: Option<(Instant, &'static str)>,
 }
 
 impl Instance {
-    pub fn new(bot_name: &str, window_id: WindowId, window_title: String) -> Self {
+    pub fn new(bot_name: &str, slot: usize, window_id: WindowId, window_title: String) -> Self {
         Self {
             id: format!("{}-{}", bot_name, window_id),
+            key: format!("{}#{}", bot_name, slot),
+            slot,
             window_id,
             window_title,
-            status: String::new(),
+            enabled: true,
+            status: Status::default(),
             error: None,
+            retries: 0,
+            last_error_at: None,
+            lateness: Duration::ZERO,
+            stuck: None,
         }
     }
+
+    /// Identity handed to the Lua VM (`F.instance`); `count` is the number of
+    /// sibling instances including this one.
+    pub fn info(&self, bot_name: &str, count: usize) -> InstanceInfo {
+        InstanceInfo {
+            bot: bot_name.to_string(),
+            id: self.id.clone(),
+            key: self.key.clone(),
+            index: self.slot,
+            count,
+        }
+    }
+
+    /// Instances for the windows matching a bot's pattern. Platforms list
+    /// windows in z-order, which every activation changes, so they are
+    /// numbered in window id order to keep each key on its window.
+    pub fn for_windows(bot_name: &str, mut windows: Vec<(WindowId, String)>) -> Vec<Instance> {
+        windows.sort_by_key(|(wid, _)| *wid);
+        windows.into_iter()
+            .enumerate()
+            .map(|(i, (wid, title))| Instance::new(bot_name, i + 1, wid, title))
+            .collect()
+    }
+
+    /// Lowest 1-based slot not taken by any of `instances`.
+    pub fn free_slot(instances: &[Instance]) -> usize {
+        (1..).find(|n| !instances.iter().any(|i| i.slot == *n)).unwrap()
+    }
+}
+
+/// What a bot VM knows about its own instance
+#[derive(Debug, Clone)]
+pub struct InstanceInfo {
+    pub bot: String,
+    pub id: String,
+    /// Stable `<bot>#<slot>` key, see `Instance::key`
+    pub key: String,
+    /// 1-based slot among the bot's instances
+    pub index: usize,
+    pub count: usize,
 }
 
 /// Orchestrator run state
@@ -75,7 +297,49 @@ pub enum OrchestratorState {
 /// Command from TUI to orchestrator
 pub enum Command {
     Toggle(usize),
+    /// Enable or disable one instance of a bot, by instance key
+    ToggleInstance { bot: usize, key: String },
     StartStop,
     Restart(usize),
+    /// Run a bot action on one instance (by id) or on all of the bot's instances
+    Action { bot: usize, instance: Option<String>, action: String },
+    /// Give up on a stuck instance (by key): leave its thread behind and
+    /// treat it as failed
+    Abandon { bot: usize, key: String },
     Quit,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(instances: &[Instance]) -> Vec<(WindowId, String)> {
+        let mut keys: Vec<_> = instances.iter().map(|i| (i.window_id, i.key.clone())).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn reordered_enumeration_keeps_keys() {
+        let first = Instance::for_windows("farm", vec![
+            (300, "c".into()), (100, "a".into()), (200, "b".into()),
+        ]);
+        // The same windows after activations shuffled the z-order
+        let second = Instance::for_windows("farm", vec![
+            (200, "b".into()), (300, "c".into()), (100, "a".into()),
+        ]);
+        assert_eq!(keys(&first), keys(&second));
+        assert_eq!(keys(&first), vec![
+            (100, "farm#1".to_string()), (200, "farm#2".to_string()), (300, "farm#3".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn free_slot_fills_gaps() {
+        let mut instances = Instance::for_windows("farm", vec![(1, "a".into()), (2, "b".into()), (3, "c".into())]);
+        instances.remove(1);
+        assert_eq!(Instance::free_slot(&instances), 2);
+        instances.push(Instance::new("farm", 2, 4, "d".into()));
+        assert_eq!(Instance::free_slot(&instances), 4);
+    }
+}
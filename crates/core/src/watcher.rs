@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often bot directories are rescanned for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What changed since the previous poll
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// Scripts whose bot folder changed
+    pub bots: Vec<PathBuf>,
+    /// Files added, edited or removed under the shared library directory
+    pub lib: Vec<PathBuf>,
+}
+
+/// Polling file watcher over bot directories and the shared library.
+/// Tracks the modification time of every file under each bot's folder, so
+/// added, edited, removed and restored files all count, and each library
+/// file on its own so callers can tell which module changed.
+pub struct BotWatcher {
+    stamps: HashMap<PathBuf, HashMap<PathBuf, SystemTime>>,
+    lib_stamps: Option<HashMap<PathBuf, SystemTime>>,
+    last_poll: Option<Instant>,
+}
+
+impl Default for BotWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BotWatcher {
+    pub fn new() -> Self {
+        Self { stamps: HashMap::new(), lib_stamps: None, last_poll: None }
+    }
+
+    /// Report bot folders and library files that changed since the previous poll.
+    /// A folder (or the library) seen for the first time only records a baseline.
+    /// Rate-limited to `POLL_INTERVAL`.
+    pub fn poll(&mut self, scripts: &[PathBuf], lib_dir: Option<&Path>) -> Changes {
+        let mut changes = Changes::default();
+        if self.last_poll.is_some_and(|t| t.elapsed() < POLL_INTERVAL) {
+            return changes;
+        }
+        self.last_poll = Some(Instant::now());
+
+        for script in scripts {
+            let Some(dir) = script.parent().filter(|d| d.is_dir()) else { continue };
+            let mut current = HashMap::new();
+            file_mtimes(dir, &mut current);
+            if self.stamps.get(dir).is_some_and(|prev| *prev != current) {
+                changes.bots.push(script.clone());
+            }
+            self.stamps.insert(dir.to_path_buf(), current);
+        }
+
+        if let Some(lib_dir) = lib_dir {
+            let mut current = HashMap::new();
+            file_mtimes(lib_dir, &mut current);
+            if let Some(prev) = self.lib_stamps.as_ref() {
+                for (path, stamp) in &current {
+                    if prev.get(path) != Some(stamp) {
+                        changes.lib.push(path.clone());
+                    }
+                }
+                changes.lib.extend(prev.keys().filter(|p| !current.contains_key(*p)).cloned());
+            }
+            self.lib_stamps = Some(current);
+        }
+        changes
+    }
+}
+
+/// Modification time of every file under `dir` (recursive, skips dotfiles).
+fn file_mtimes(dir: &Path, out: &mut HashMap<PathBuf, SystemTime>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            file_mtimes(&path, out);
+        } else if let Ok(stamp) = entry.metadata().and_then(|m| m.modified()) {
+            out.insert(path, stamp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A bot folder with main.lua and a module next to it
+    fn bot(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("finger-watch-{}-{}", std::process::id(), name));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.lua"), "return require('util')").unwrap();
+        std::fs::write(dir.join("util.lua"), "return {}").unwrap();
+        dir.join("main.lua")
+    }
+
+    fn poll(watcher: &mut BotWatcher, script: &Path) -> Vec<PathBuf> {
+        // Skip the rate limit
+        watcher.last_poll = None;
+        watcher.poll(&[script.to_path_buf()], None).bots
+    }
+
+    #[test]
+    fn edits_and_removals_are_changes() {
+        let script = bot("remove");
+        let mut watcher = BotWatcher::new();
+        assert!(poll(&mut watcher, &script).is_empty());
+        assert!(poll(&mut watcher, &script).is_empty());
+        std::fs::remove_file(script.with_file_name("util.lua")).unwrap();
+        assert_eq!(poll(&mut watcher, &script), vec![script.clone()]);
+        assert!(poll(&mut watcher, &script).is_empty());
+        std::fs::write(script.with_file_name("new.lua"), "").unwrap();
+        assert_eq!(poll(&mut watcher, &script), vec![script.clone()]);
+        std::fs::remove_dir_all(script.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn restoring_an_older_file_is_a_change() {
+        let script = bot("restore");
+        let util = script.with_file_name("util.lua");
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        let mut watcher = BotWatcher::new();
+        poll(&mut watcher, &script);
+        std::fs::write(&util, "return { restored = true }").unwrap();
+        std::fs::File::options().write(true).open(&util).unwrap().set_modified(old).unwrap();
+        assert_eq!(poll(&mut watcher, &script), vec![script.clone()]);
+        std::fs::remove_dir_all(script.parent().unwrap()).ok();
+    }
+}
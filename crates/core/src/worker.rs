@@ -0,0 +1,576 @@
+use std::any::Any;
+use std::collections::BTreeSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, mpsc};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde_json::{Map, Value};
+
+use crate::types::*;
+use crate::bus::Message;
+use crate::cancel;
+use crate::logger;
+use crate::lua_rt::{LuaBot, Trace};
+use crate::platform::WindowHandle;
+
+/// How often a worker waiting for the foreground checks for a stop request
+const FOCUS_POLL: Duration = Duration::from_millis(10);
+
+/// Serial of the next worker, so events from a stopped worker are not
+/// mistaken for those of its replacement
+static NEXT_SERIAL: AtomicU64 = AtomicU64::new(0);
+
+/// Turns of the workers waiting for the foreground, and the one that has it
+struct Focus {
+    waiting: BTreeSet<u64>,
+    holder: Option<u64>,
+}
+
+static FOCUS: Mutex<Focus> = Mutex::new(Focus { waiting: BTreeSet::new(), holder: None });
+static FOCUS_FREED: Condvar = Condvar::new();
+
+/// Held while a worker activates its window and runs tick(): only one
+/// instance at a time may have the foreground and send input.
+struct FocusGuard(u64);
+
+impl FocusGuard {
+    /// Wait for the foreground. Waiters get it in order of `turn`, which the
+    /// orchestrator hands out in scheduler order. None on a stop request.
+    fn acquire(turn: u64) -> Option<Self> {
+        let mut focus = FOCUS.lock().unwrap();
+        focus.waiting.insert(turn);
+        while focus.holder.is_some() || focus.waiting.first() != Some(&turn) {
+            if cancel::requested() {
+                focus.waiting.remove(&turn);
+                FOCUS_FREED.notify_all();
+                return None;
+            }
+            focus = FOCUS_FREED.wait_timeout(focus, FOCUS_POLL).unwrap().0;
+        }
+        focus.waiting.remove(&turn);
+        focus.holder = Some(turn);
+        Some(FocusGuard(turn))
+    }
+
+    /// Hand the foreground on if the run of `turn` still has it.
+    fn release(turn: u64) {
+        let mut focus = FOCUS.lock().unwrap();
+        if focus.holder == Some(turn) {
+            focus.holder = None;
+            FOCUS_FREED.notify_all();
+        }
+    }
+}
+
+impl Drop for FocusGuard {
+    fn drop(&mut self) {
+        Self::release(self.0);
+    }
+}
+
+/// What an instance's thread is busy with and since when, for the watchdog
+#[derive(Default)]
+struct Activity(Mutex<Option<(Instant, &'static str)>>);
+
+impl Activity {
+    fn begin(&self, step: &'static str) {
+        *self.0.lock().unwrap() = Some((Instant::now(), step));
+    }
+
+    fn end(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
+/// Work the orchestrator hands to an instance's thread
+enum Job {
+    Run { turn: u64, lateness: Duration },
+    CheckWindow,
+    Deliver(Message),
+    Action(String),
+    InstanceCount(usize),
+    Reset,
+    Reload { script_path: PathBuf, win: Box<dyn WindowHandle>, opts: Map<String, Value> },
+    Stop,
+}
+
+/// How one run of an instance ended
+pub enum Outcome {
+    /// observe() handled it in the background; seconds until the next run
+    Observed(f64, Option<Status>),
+    /// tick() ran in the foreground; seconds until the next run
+    Ticked(f64, Option<Status>),
+    /// verify_activation found the window still behind
+    Unfocused,
+    /// A stop request aborted the run
+    Cancelled,
+    /// The bot raised an error or panicked; on_error queued the failure
+    Failed,
+}
+
+/// What the orchestrator loop waits on: its commands, and reports from the
+/// instance threads.
+pub enum Event {
+    Command(Command),
+    /// A run finished; `open` is false once the window is gone
+    Ran { id: String, serial: u64, outcome: Outcome, lateness: Duration, open: bool },
+    /// A window check between runs finished
+    Checked { id: String, serial: u64, open: bool },
+    /// A bus message or action was queued; the instance wants to run now
+    Woken { id: String, serial: u64 },
+}
+
+/// Handle to the thread running one bot instance. The LuaBot lives on that
+/// thread, so a slow or stuck bot only holds up itself, and a panic in the
+/// bot or the platform becomes that instance's error.
+pub struct Worker {
+    pub serial: u64,
+    /// A run was sent and has not reported back yet
+    pub busy: bool,
+    /// The watchdog found the step in progress stuck; stop() will not wait
+    /// for it
+    pub stuck: bool,
+    /// The watchdog has logged where the stuck step is
+    pub traced: bool,
+    id: String,
+    bot_name: String,
+    /// Turn of the run in progress, so an abandoned run gives up the foreground
+    turn: u64,
+    jobs: mpsc::Sender<Job>,
+    stop: Arc<AtomicBool>,
+    modules: Arc<Mutex<Vec<PathBuf>>>,
+    activity: Arc<Activity>,
+    trace: Arc<Trace>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    /// Start the thread for one instance and load its bot there, waiting for
+    /// start(win) to return. None if that failed; on_error has fired.
+    pub fn spawn(
+        script_path: PathBuf,
+        info: InstanceInfo,
+        win: Box<dyn WindowHandle>,
+        opts: Map<String, Value>,
+        on_error: Arc<dyn Fn(Vec<String>) + Send + Sync>,
+        events: mpsc::Sender<Event>,
+    ) -> Option<Self> {
+        let serial = NEXT_SERIAL.fetch_add(1, Ordering::Relaxed);
+        let id = info.id.clone();
+        let bot_name = info.bot.clone();
+        let (jobs, job_rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let modules = Arc::new(Mutex::new(Vec::new()));
+        let activity = Arc::new(Activity::default());
+        let runner = Runner {
+            id: info.id.clone(),
+            serial,
+            events,
+            stop: Arc::clone(&stop),
+            modules: Arc::clone(&modules),
+            activity: Arc::clone(&activity),
+            on_error: Arc::clone(&on_error),
+        };
+        let spawned = thread::Builder::new().name(info.id.clone()).spawn(move || {
+            cancel::bind(Arc::clone(&runner.stop));
+            let bot = runner.contain(|| {
+                LuaBot::new(&script_path, info, win, opts, Arc::clone(&runner.on_error)).ok()
+            }).flatten();
+            let Some(bot) = bot else {
+                ready_tx.send(None).ok();
+                return;
+            };
+            runner.note_modules(&bot);
+            ready_tx.send(Some(bot.trace())).ok();
+            runner.serve(bot, job_rx);
+        });
+        let thread = match spawned {
+            Ok(t) => t,
+            Err(e) => {
+                on_error(vec![format!("cannot start thread: {}", e)]);
+                return None;
+            }
+        };
+        let Ok(Some(trace)) = ready_rx.recv() else {
+            thread.join().ok();
+            return None;
+        };
+        Some(Self {
+            serial, busy: false, stuck: false, traced: false, id, bot_name, turn: 0,
+            jobs, stop, modules, activity, trace, thread: Some(thread),
+        })
+    }
+
+    pub fn bot_name(&self) -> &str {
+        &self.bot_name
+    }
+
+    /// Observe or tick once; the instance reports back with `Event::Ran`.
+    /// Runs that need the foreground get it in order of `turn`.
+    pub fn run(&mut self, turn: u64, lateness: Duration) {
+        self.busy = true;
+        self.turn = turn;
+        self.send(Job::Run { turn, lateness });
+    }
+
+    /// Refresh the window and deliver its events; reports `Event::Checked`.
+    pub fn check_window(&self) {
+        self.send(Job::CheckWindow);
+    }
+
+    pub fn deliver(&self, msg: &Message) {
+        self.send(Job::Deliver(msg.clone()));
+    }
+
+    pub fn queue_action(&self, name: &str) {
+        self.send(Job::Action(name.to_string()));
+    }
+
+    pub fn set_instance_count(&self, count: usize) {
+        self.send(Job::InstanceCount(count));
+    }
+
+    pub fn reset(&self) {
+        self.send(Job::Reset);
+    }
+
+    /// Hot-swap the VM on the instance's thread; the outcome is logged there.
+    pub fn reload(&self, script_path: &Path, win: Box<dyn WindowHandle>, opts: Map<String, Value>) {
+        self.send(Job::Reload { script_path: script_path.to_path_buf(), win, opts });
+    }
+
+    /// Whether the bot has require()d the module file at `file`, as of its
+    /// last run.
+    pub fn requires(&self, file: &Path) -> bool {
+        let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        self.modules.lock().unwrap().contains(&file)
+    }
+
+    /// The step of the run in progress and when it began; None between
+    /// steps, e.g. while waiting for the foreground.
+    pub fn activity(&self) -> Option<(Instant, &'static str)> {
+        *self.activity.0.lock().unwrap()
+    }
+
+    /// Where the bot's Lua code is, as far as it is known.
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    /// Give up on a run that does not return: ask it to stop, free the
+    /// foreground for the other instances and leave the thread behind
+    /// without waiting. Its later reports carry a stale serial.
+    pub fn abandon(mut self) {
+        self.stop.store(true, Ordering::Release);
+        self.send(Job::Stop);
+        FocusGuard::release(self.turn);
+        self.thread.take();
+    }
+
+    /// Abort the run in progress, if any, call stop() and wait for the
+    /// thread to end. A stuck worker is abandoned instead, since waiting
+    /// could take forever.
+    pub fn stop(self) {
+        if self.stuck {
+            logger::warn_p(&self.id, "stuck, leaving its thread behind");
+            self.abandon();
+            return;
+        }
+        self.shut_down();
+        self.join();
+    }
+
+    /// Wait for the thread to end; without it, dropping the handle does not
+    /// send Stop again.
+    fn join(mut self) {
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+
+    fn shut_down(&self) {
+        if self.busy {
+            self.stop.store(true, Ordering::Release);
+        }
+        self.send(Job::Stop);
+    }
+
+    fn send(&self, job: Job) {
+        // The thread is gone only after Stop, or if it could not start
+        self.jobs.send(job).ok();
+    }
+}
+
+/// Stop all of `workers` after a stop request. Every run is aborted before
+/// any worker is waited for, so none can take the foreground and send input
+/// while the others are being stopped. The request is then cleared so each
+/// stop() can run within its own budget.
+pub fn stop_all(workers: impl IntoIterator<Item = Worker>) {
+    let mut stopping = Vec::new();
+    for worker in workers {
+        if worker.stuck {
+            logger::warn_p(&worker.id, "stuck, leaving its thread behind");
+            worker.abandon();
+            continue;
+        }
+        worker.stop.store(true, Ordering::Release);
+        worker.send(Job::Stop);
+        stopping.push(worker);
+    }
+    cancel::clear();
+    for worker in stopping {
+        worker.join();
+    }
+}
+
+impl Drop for Worker {
+    /// A worker dropped without `stop` still stops, just without waiting.
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.shut_down();
+        }
+    }
+}
+
+/// The instance thread's side of a worker
+struct Runner {
+    id: String,
+    serial: u64,
+    events: mpsc::Sender<Event>,
+    stop: Arc<AtomicBool>,
+    modules: Arc<Mutex<Vec<PathBuf>>>,
+    activity: Arc<Activity>,
+    on_error: Arc<dyn Fn(Vec<String>) + Send + Sync>,
+}
+
+impl Runner {
+    /// Handle jobs until Stop, or until the orchestrator drops the worker.
+    fn serve(&self, mut bot: LuaBot, jobs: mpsc::Receiver<Job>) {
+        for job in jobs {
+            if let Job::Stop = job {
+                break;
+            }
+            self.handle(&mut bot, job);
+        }
+        // The aborted run is over; stop() itself runs as usual
+        self.stop.store(false, Ordering::Release);
+        let stopped = panic::catch_unwind(AssertUnwindSafe(|| bot.stop()));
+        if let Err(payload) = stopped {
+            logger::error_p(&self.id, &format!("stop() panicked: {}", panic_message(&*payload)));
+        }
+    }
+
+    fn handle(&self, bot: &mut LuaBot, job: Job) {
+        match job {
+            Job::Run { turn, lateness } => {
+                let outcome = self.contain(|| run(bot, turn, &self.activity)).unwrap_or(Outcome::Failed);
+                self.activity.end();
+                let open = match outcome {
+                    Outcome::Failed | Outcome::Cancelled => true,
+                    _ => self.check(bot),
+                };
+                self.note_modules(bot);
+                self.report(|id, serial| Event::Ran { id, serial, outcome, lateness, open });
+            }
+            Job::CheckWindow => {
+                let open = self.check(bot);
+                self.report(|id, serial| Event::Checked { id, serial, open });
+            }
+            Job::Deliver(msg) => {
+                if self.contain(|| bot.deliver(&msg)).unwrap_or(false) {
+                    self.report(|id, serial| Event::Woken { id, serial });
+                }
+            }
+            Job::Action(name) => {
+                self.contain(|| bot.queue_action(&name));
+                self.report(|id, serial| Event::Woken { id, serial });
+            }
+            Job::InstanceCount(count) => {
+                self.contain(|| bot.set_instance_count(count));
+            }
+            Job::Reset => {
+                self.contain(|| bot.reset().ok());
+            }
+            Job::Reload { script_path, win, opts } => {
+                self.activity.begin("reload");
+                match self.contain(|| bot.reload(&script_path, win, opts)) {
+                    Some(Ok(())) => logger::info(&format!("reloaded {}", self.id)),
+                    Some(Err(e)) => logger::error(&format!(
+                        "reload {} failed, keeping old VM:\n  {}", self.id, e
+                    )),
+                    None => {}
+                }
+                self.activity.end();
+                self.note_modules(bot);
+            }
+            Job::Stop => {}
+        }
+    }
+
+    /// Run the window check; errors in the callbacks went to on_error.
+    fn check(&self, bot: &mut LuaBot) -> bool {
+        self.activity.begin("window check");
+        let open = self.contain(|| bot.check_window().unwrap_or(true)).unwrap_or(true);
+        self.activity.end();
+        open
+    }
+
+    fn report(&self, event: impl FnOnce(String, u64) -> Event) {
+        self.events.send(event(self.id.clone(), self.serial)).ok();
+    }
+
+    fn note_modules(&self, bot: &LuaBot) {
+        let files = bot.required_files();
+        *self.modules.lock().unwrap() = files;
+    }
+
+    /// Run `f`, turning a panic into a runtime error of this instance.
+    fn contain<T>(&self, f: impl FnOnce() -> T) -> Option<T> {
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(out) => Some(out),
+            Err(payload) => {
+                (self.on_error)(vec![format!("panic: {}", panic_message(&*payload))]);
+                None
+            }
+        }
+    }
+}
+
+/// One pass of an instance, as the orchestrator used to run it inline:
+/// observe() in the background if the bot can, otherwise take the
+/// foreground in turn and tick(). Each step is noted in `activity` as it
+/// starts.
+fn run(bot: &mut LuaBot, turn: u64, activity: &Activity) -> Outcome {
+    if bot.observes() {
+        activity.begin("observe");
+        bot.capture_frame();
+        let observed = bot.observe();
+        activity.end();
+        match observed {
+            Ok(Some(cd)) => {
+                let status = bot.get_status().ok();
+                let cd = bot.next_timer().map_or(cd, |t| cd.min(t));
+                return Outcome::Observed(cd, status);
+            }
+            // Wants input: take the foreground and tick
+            Ok(None) => {}
+            Err(_) => return Outcome::Failed,
+        }
+    }
+
+    let Some(_focus) = FocusGuard::acquire(turn) else { return Outcome::Cancelled };
+    activity.begin("activate");
+    bot.set_active(true);
+    let focused = bot.activate();
+    if cancel::requested() {
+        bot.set_active(false);
+        return Outcome::Cancelled;
+    }
+    if !focused {
+        bot.set_active(false);
+        return Outcome::Unfocused;
+    }
+
+    activity.begin("tick");
+    let tick_result = bot.tick();
+    let status = if tick_result.is_ok() { bot.get_status().ok() } else { None };
+    let next_timer = bot.next_timer();
+    bot.set_active(false);
+    match tick_result {
+        // Wake up early for the next F.every/F.after timer
+        Ok(cd) => Outcome::Ticked(next_timer.map_or(cd, |t| cd.min(t)), status),
+        Err(_) if cancel::requested() => Outcome::Cancelled,
+        // on_error fired inside lua_rt and queued the failure
+        Err(_) => Outcome::Failed,
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::KeyPause;
+
+    /// Always in front; notes which window each click went to and when
+    struct Clicks {
+        id: WindowId,
+        log: Arc<Mutex<Vec<(WindowId, Instant)>>>,
+    }
+
+    impl WindowHandle for Clicks {
+        fn id(&self) -> WindowId { self.id }
+        fn title(&self) -> &str { "Game" }
+        fn region(&self) -> Option<Region> {
+            Some(Region { l: 0, t: 0, r: 800, b: 600, w: 800, h: 600, cx: 400, cy: 300 })
+        }
+        fn update(&mut self) {}
+        fn activate(&mut self) -> bool { false }
+        fn is_foreground(&self) -> bool { true }
+
+        fn click_relative(&mut self, _x_ratio: f64, _y_ratio: f64) -> Result<(), InputError> {
+            self.log.lock().unwrap().push((self.id, Instant::now()));
+            Ok(())
+        }
+
+        fn tap(&mut self, _key: &str) -> Result<(), InputError> { Ok(()) }
+        fn tap_timed(&mut self, _key: &str, _pause: &mut dyn FnMut(KeyPause) -> u64) -> Result<(), InputError> { Ok(()) }
+        fn type_text(&mut self, _text: &str) -> Result<(), InputError> { Ok(()) }
+        fn capture(&mut self, _rect: Option<CaptureRect>) -> Option<Capture> { None }
+    }
+
+    #[test]
+    fn stopping_leaves_no_worker_free_to_send_input() {
+        let dir = std::env::temp_dir().join(format!("finger-worker-{}-stop", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("main.lua");
+        // Clicks until it is stopped, holding the foreground all along
+        std::fs::write(&script, r#"
+            local win
+            return {
+                window_pattern = "x", description = "",
+                start = function(w) win = w end,
+                tick = function()
+                    while true do
+                        win:click(0.5, 0.5)
+                        F.delay(5)
+                    end
+                end,
+            }
+        "#).unwrap();
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (events, _event_rx) = mpsc::channel();
+        let mut workers: Vec<Worker> = (1..=2).map(|slot| {
+            let inst = Instance::new("clicker", slot, slot as WindowId, "Game".into());
+            let win = Box::new(Clicks { id: inst.window_id, log: Arc::clone(&log) });
+            Worker::spawn(script.clone(), inst.info("clicker", 2), win, Map::new(), Arc::new(|_| {}), events.clone())
+                .unwrap()
+        }).collect();
+
+        workers[0].run(1, Duration::ZERO);
+        while log.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(5));
+        }
+        // The second one waits for the foreground the first one holds
+        workers[1].run(2, Duration::ZERO);
+        thread::sleep(Duration::from_millis(50));
+
+        stop_all(workers);
+        let stopped = Instant::now();
+        thread::sleep(Duration::from_millis(100));
+
+        let log = log.lock().unwrap();
+        assert!(log.iter().all(|(id, _)| *id == 1), "the second worker clicked");
+        assert!(log.iter().all(|(_, at)| *at < stopped));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -57,9 +57,9 @@ fn save_screenshot_as_jpg(cap: &Capture, path: &str) -> Result<(), Box<dyn std::
 
 /// Cmd/Ctrl+S, then read `logs/test.txt` back.
 fn save_and_read(win: &mut Box<dyn WindowHandle>) -> Option<String> {
-    win.tap(save_key());
+    win.tap(save_key()).ok()?;
     sleep(0.3);
-    if IS_DARWIN { win.tap("enter"); sleep(0.2); } // dismiss "Keep formatting?" sheet
+    if IS_DARWIN { win.tap("enter").ok()?; sleep(0.2); } // dismiss "Keep formatting?" sheet
     fs::read_to_string("logs/test.txt").ok()
 }
 
@@ -151,12 +151,12 @@ fn test_03_window_activation() -> Result<(), Failed> {
 
 fn test_04_keyboard_tap() -> Result<(), Failed> {
     let mut win = setup_window()?;
-    win.tap(select_all_key()); win.tap("delete"); sleep(0.2);
+    win.tap(select_all_key())?; win.tap("delete")?; sleep(0.2);
     println!("  tap a, b, left, c → expect \"acb\"");
-    win.tap("a"); sleep(0.1);
-    win.tap("b"); sleep(0.1);
-    win.tap("left"); sleep(0.1);
-    win.tap("c"); sleep(0.3);
+    win.tap("a")?; sleep(0.1);
+    win.tap("b")?; sleep(0.1);
+    win.tap("left")?; sleep(0.1);
+    win.tap("c")?; sleep(0.3);
     let got = save_and_read(&mut win).ok_or_else(|| Failed::from("could not read test.txt"))?;
     if got != "acb" {
         return Err(Failed::from(format!("expected \"acb\", got {:?}", got)));
@@ -166,10 +166,10 @@ fn test_04_keyboard_tap() -> Result<(), Failed> {
 
 fn test_05_keyboard_type() -> Result<(), Failed> {
     let mut win = setup_window()?;
-    win.tap(select_all_key()); win.tap("delete"); sleep(0.3);
+    win.tap(select_all_key())?; win.tap("delete")?; sleep(0.3);
     let text = "Hello World 123";
     println!("  type_text({text:?})");
-    win.type_text(text); sleep(0.3);
+    win.type_text(text)?; sleep(0.3);
     let got = save_and_read(&mut win).ok_or_else(|| Failed::from("could not read test.txt"))?;
     if got != text {
         return Err(Failed::from(format!("expected {text:?}, got {got:?}")));
@@ -179,10 +179,10 @@ fn test_05_keyboard_type() -> Result<(), Failed> {
 
 fn test_06_keyboard_send() -> Result<(), Failed> {
     let mut win = setup_window()?;
-    win.tap(select_all_key()); win.tap("delete"); sleep(0.3);
+    win.tap(select_all_key())?; win.tap("delete")?; sleep(0.3);
     let text = "Test Command";
     println!("  type_text({text:?}) + enter");
-    win.type_text(text); win.tap("enter"); sleep(0.3);
+    win.type_text(text)?; win.tap("enter")?; sleep(0.3);
     let raw = save_and_read(&mut win).ok_or_else(|| Failed::from("could not read test.txt"))?;
     let norm = raw.replace("\r\n", "\n").replace('\r', "\n");
     if norm != format!("{text}\n") && norm != text {
@@ -196,13 +196,13 @@ fn test_06_keyboard_send() -> Result<(), Failed> {
 
 fn test_07_keyboard_special_keys() -> Result<(), Failed> {
     let mut win = setup_window()?;
-    win.tap(select_all_key()); win.tap("delete"); sleep(0.3);
+    win.tap(select_all_key())?; win.tap("delete")?; sleep(0.3);
     println!("  a→space→b→enter→c→d→left→up→space→down→right→f");
     for (key, delay) in &[
         ("a", 0.05), ("space", 0.05), ("b", 0.05), ("enter", 0.05),
         ("c", 0.05), ("d", 0.05), ("left", 0.05), ("up", 0.05),
         ("space", 0.05), ("down", 0.05), ("right", 0.05), ("f", 0.3),
-    ] { win.tap(key); sleep(*delay); }
+    ] { win.tap(key)?; sleep(*delay); }
     let raw = save_and_read(&mut win).ok_or_else(|| Failed::from("could not read test.txt"))?;
     let norm = raw.replace("\r\n", "\n").replace('\r', "\n");
     // Accept autocapitalize (A vs a) and single/double space (macOS cursor math)
@@ -218,19 +218,19 @@ fn test_07_keyboard_special_keys() -> Result<(), Failed> {
 
 fn test_08_mouse_click() -> Result<(), Failed> {
     let mut win = setup_window()?;
-    win.tap(select_all_key()); win.tap("delete"); sleep(0.3);
+    win.tap(select_all_key())?; win.tap("delete")?; sleep(0.3);
     let region = win.region().ok_or_else(|| Failed::from("region unavailable"))?;
-    win.type_text("Click Test"); sleep(0.3);
+    win.type_text("Click Test")?; sleep(0.3);
     // Move left 5 → cursor before space: "Click| Test"
-    for _ in 0..5 { win.tap("left"); sleep(0.05); }
+    for _ in 0..5 { win.tap("left")?; sleep(0.05); }
     sleep(0.2);
     // Click window center: single short line → cursor moves past end
     let (rx, ry) = (0.5, 0.5);
     println!("  click_relative({rx}, {ry}) → ({}, {}) screen px",
         region.l + (rx * region.w as f64) as i32,
         region.t + (ry * region.h as f64) as i32);
-    win.click_relative(rx, ry); sleep(0.2);
-    win.type_text(" Works"); sleep(0.3);
+    win.click_relative(rx, ry)?; sleep(0.2);
+    win.type_text(" Works")?; sleep(0.3);
     let got = save_and_read(&mut win).ok_or_else(|| Failed::from("could not read test.txt"))?;
     if got != "Click Test Works" {
         return Err(Failed::from(format!("expected \"Click Test Works\", got {got:?}")));
@@ -240,15 +240,15 @@ fn test_08_mouse_click() -> Result<(), Failed> {
 
 fn test_09_mouse_click_relative() -> Result<(), Failed> {
     let mut win = setup_window()?;
-    win.tap(select_all_key()); win.tap("delete"); sleep(0.3);
-    win.type_text("Relative Test"); sleep(0.3);
+    win.tap(select_all_key())?; win.tap("delete")?; sleep(0.3);
+    win.type_text("Relative Test")?; sleep(0.3);
     // Move left 5 → cursor before space: "Relative| Test"
-    for _ in 0..5 { win.tap("left"); sleep(0.05); }
+    for _ in 0..5 { win.tap("left")?; sleep(0.05); }
     sleep(0.2);
     // Click window center → cursor past end of text
     println!("  click_relative(0.5, 0.5) — window center");
-    win.click_relative(0.5, 0.5); sleep(0.2);
-    win.type_text(" Works"); sleep(0.3);
+    win.click_relative(0.5, 0.5)?; sleep(0.2);
+    win.type_text(" Works")?; sleep(0.3);
     let got = save_and_read(&mut win).ok_or_else(|| Failed::from("could not read test.txt"))?;
     if got != "Relative Test Works" {
         return Err(Failed::from(format!("expected \"Relative Test Works\", got {got:?}")));
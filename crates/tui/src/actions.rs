@@ -0,0 +1,102 @@
+use ratatui::{
+    Frame,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use finger_core::types::BotEntry;
+
+use crate::confirm::centered_rect;
+
+/// Modal list of the actions a bot exports, run on all or one instance.
+pub struct ActionMenu {
+    pub bot: usize,
+    /// 0 = every instance, n = the bot's n-th instance
+    pub scope: usize,
+    pub selected: usize,
+}
+
+impl ActionMenu {
+    pub fn new(bot: usize) -> Self {
+        Self { bot, scope: 0, selected: 0 }
+    }
+
+    /// Instance id of the current scope, None when targeting every instance.
+    pub fn target(&self, entry: &BotEntry) -> Option<String> {
+        match self.scope {
+            0 => None,
+            n => entry.instances.get(n - 1).map(|i| i.id.clone()),
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self, entry: &BotEntry) {
+        if self.selected + 1 < entry.actions.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn next_scope(&mut self, entry: &BotEntry) {
+        self.scope = (self.scope + 1) % (entry.instances.len() + 1);
+    }
+
+    pub fn render(&self, f: &mut Frame, entry: &BotEntry) {
+        let height = entry.actions.len() as u16 + 6;
+        let area = centered_rect(52, height, f.area());
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(format!(" Actions: {} ", entry.name));
+
+        let mut lines: Vec<Line> = Vec::new();
+
+        // Target selector: every instance, then one tab per instance
+        let mut scopes = vec![Span::styled(" run on ", Style::default().fg(Color::DarkGray))];
+        let labels = std::iter::once("all".to_string())
+            .chain(entry.instances.iter().map(|i| format!("#{}", i.slot)));
+        for (i, label) in labels.enumerate() {
+            let style = if i == self.scope {
+                Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Cyan)
+            };
+            scopes.push(Span::styled(format!(" {} ", label), style));
+            scopes.push(Span::raw(" "));
+        }
+        lines.push(Line::from(scopes));
+        lines.push(Line::from(""));
+
+        for (i, action) in entry.actions.iter().enumerate() {
+            let is_selected = i == self.selected;
+            let style = if is_selected {
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            lines.push(Line::from(vec![
+                Span::raw(if is_selected { " > " } else { "   " }),
+                Span::styled(action.label.clone(), style),
+            ]));
+        }
+
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(" ↑↓", Style::default().fg(Color::Yellow)),
+            Span::raw(" move  "),
+            Span::styled("tab", Style::default().fg(Color::Yellow)),
+            Span::raw(" target  "),
+            Span::styled("enter", Style::default().fg(Color::Yellow)),
+            Span::raw(" run  "),
+            Span::styled("esc", Style::default().fg(Color::Yellow)),
+            Span::raw(" close"),
+        ]));
+
+        f.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
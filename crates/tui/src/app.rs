@@ -2,14 +2,21 @@ use std::path::PathBuf;
 use std::sync::{Arc, Mutex, mpsc};
 use crossterm::{execute, event::{EnableMouseCapture, DisableMouseCapture}};
 use finger_core::types::{BotEntry, Command, OrchestratorState};
+use finger_core::{logger, orchestrator};
+use finger_core::platform::Platform;
+use finger_core::recorder::Recorder;
 use finger_core::settings::Settings;
 
+use crate::actions::ActionMenu;
 use crate::confirm::ConfirmDialog;
+use crate::options::OptionsEditor;
 
 pub struct App {
     pub state: Arc<Mutex<Vec<BotEntry>>>,
     pub orch_state: Arc<Mutex<OrchestratorState>>,
     pub selected: usize,
+    /// Instance row under the cursor within the selected bot, if any
+    pub selected_instance: Option<usize>,
     pub log_visible: bool,
     pub mouse_capture: bool,
     pub log_messages: Vec<String>,
@@ -18,9 +25,23 @@ pub struct App {
     pub cmd_tx: mpsc::Sender<Command>,
     pub settings_path: PathBuf,
     pub confirm: Option<ConfirmDialog>,
+    /// Stuck instance (bot index, instance key) the confirm dialog asks about;
+    /// None when it asks to restart everything
+    pub abandon: Option<(usize, String)>,
+    pub options: Option<OptionsEditor>,
+    pub actions: Option<ActionMenu>,
+    pub recording: Option<ActiveRecording>,
     pub should_quit: bool,
 }
 
+/// A macro recording started from the TUI, saved as a new bot next to the
+/// one it was started from.
+pub struct ActiveRecording {
+    pub recorder: Recorder,
+    bots_dir: PathBuf,
+    name: String,
+}
+
 impl App {
     pub fn new(
         state: Arc<Mutex<Vec<BotEntry>>>,
@@ -33,6 +54,7 @@ impl App {
             state,
             orch_state,
             selected: 0,
+            selected_instance: None,
             log_visible: true,
             mouse_capture: true,
             log_messages: Vec::new(),
@@ -41,6 +63,10 @@ impl App {
             cmd_tx,
             settings_path,
             confirm: None,
+            abandon: None,
+            options: None,
+            actions: None,
+            recording: None,
             should_quit: false,
         }
     }
@@ -75,44 +101,204 @@ impl App {
         self.log_scroll = self.log_scroll.saturating_sub(n);
     }
 
-    pub fn move_up(&mut self) {
-        if self.selected > 0 {
-            self.selected -= 1;
+    /// Rows of the bot list as (bot, instance): each bot, followed by its
+    /// instances while it is enabled.
+    fn rows(&self) -> Vec<(usize, Option<usize>)> {
+        let entries = self.state.lock().unwrap();
+        let mut rows = Vec::new();
+        for (i, entry) in entries.iter().enumerate() {
+            rows.push((i, None));
+            if entry.enabled {
+                rows.extend((0..entry.instances.len()).map(|n| (i, Some(n))));
+            }
         }
+        rows
     }
 
-    pub fn move_down(&mut self) {
-        let len = self.state.lock().unwrap().len();
-        if self.selected + 1 < len {
-            self.selected += 1;
+    fn move_by(&mut self, up: bool) {
+        let rows = self.rows();
+        // The instance row may have gone with its window; fall back to the bot
+        let current = rows.iter()
+            .position(|r| *r == (self.selected, self.selected_instance))
+            .or_else(|| rows.iter().position(|r| *r == (self.selected, None)))
+            .unwrap_or(0);
+        let next = if up { current.saturating_sub(1) } else { (current + 1).min(rows.len().saturating_sub(1)) };
+        if let Some(&(bot, inst)) = rows.get(next) {
+            self.selected = bot;
+            self.selected_instance = inst;
         }
     }
 
+    pub fn move_up(&mut self) {
+        self.move_by(true);
+    }
+
+    pub fn move_down(&mut self) {
+        self.move_by(false);
+    }
+
+    /// Switch the instance under the cursor on or off, or the whole bot on a
+    /// bot row. The bot switch only works while stopped; instances can be
+    /// switched while running.
     pub fn toggle_selected(&mut self) {
+        if let Some(n) = self.selected_instance {
+            let key = {
+                let mut entries = self.state.lock().unwrap();
+                let Some(inst) = entries.get_mut(self.selected)
+                    .and_then(|e| e.instances.get_mut(n)) else { return };
+                inst.enabled = !inst.enabled;
+                inst.key.clone()
+            };
+            self.save_settings();
+            self.cmd_tx.send(Command::ToggleInstance { bot: self.selected, key }).ok();
+            return;
+        }
+        if !self.is_stopped() {
+            return;
+        }
         {
             let mut entries = self.state.lock().unwrap();
             if let Some(entry) = entries.get_mut(self.selected) {
                 entry.enabled = !entry.enabled;
             }
-            let enabled_bots: Vec<String> = entries.iter()
-                .filter(|e| e.enabled)
-                .map(|e| e.name.clone())
-                .collect();
-            Settings { enabled_bots }.save(&self.settings_path);
         }
+        self.save_settings();
         self.cmd_tx.send(Command::Toggle(self.selected)).ok();
     }
 
-    pub fn start_stop(&mut self) {
-        {
-            let mut os = self.orch_state.lock().unwrap();
-            match *os {
-                OrchestratorState::Running => *os = OrchestratorState::Stopping,
-                OrchestratorState::Stopped => *os = OrchestratorState::Running,
-                OrchestratorState::Stopping => return,
+    /// Persist enabled bots and instances and option overrides, keeping
+    /// hand-edited fields such as `humanize` as they are on disk.
+    pub fn save_settings(&self) {
+        let entries = self.state.lock().unwrap();
+        let mut settings = Settings::load(&self.settings_path);
+        settings.update(&entries);
+        settings.save(&self.settings_path);
+    }
+
+    /// Open the options form for the selected bot, if it declares any.
+    pub fn open_options(&mut self) {
+        let has_options = self.state.lock().unwrap()
+            .get(self.selected)
+            .is_some_and(|e| !e.options.is_empty());
+        if has_options {
+            self.options = Some(OptionsEditor::new(self.selected));
+        }
+    }
+
+    /// Apply an edit from the options form to its bot entry.
+    /// Settings are saved when `f` reports a change.
+    pub fn edit_options(&mut self, f: impl FnOnce(&mut OptionsEditor, &mut BotEntry) -> bool) {
+        let Some(editor) = self.options.as_mut() else { return };
+        let changed = {
+            let mut entries = self.state.lock().unwrap();
+            let Some(entry) = entries.get_mut(editor.bot) else { return };
+            f(editor, entry)
+        };
+        if changed {
+            self.save_settings();
+        }
+    }
+
+    pub fn close_options(&mut self) {
+        self.options = None;
+    }
+
+    /// Open the action menu for the selected bot, if it exports any.
+    pub fn open_actions(&mut self) {
+        let has_actions = self.state.lock().unwrap()
+            .get(self.selected)
+            .is_some_and(|e| !e.actions.is_empty());
+        if has_actions {
+            let mut menu = ActionMenu::new(self.selected);
+            // Target the instance under the cursor
+            menu.scope = self.selected_instance.map_or(0, |n| n + 1);
+            self.actions = Some(menu);
+        }
+    }
+
+    /// Navigate the action menu against its bot entry.
+    pub fn edit_actions(&mut self, f: impl FnOnce(&mut ActionMenu, &BotEntry)) {
+        let Some(menu) = self.actions.as_mut() else { return };
+        let entries = self.state.lock().unwrap();
+        if let Some(entry) = entries.get(menu.bot) {
+            f(menu, entry);
+        }
+    }
+
+    /// Send the selected action to the orchestrator and close the menu.
+    pub fn run_action(&mut self) {
+        let Some(menu) = self.actions.take() else { return };
+        let cmd = {
+            let entries = self.state.lock().unwrap();
+            let Some(entry) = entries.get(menu.bot) else { return };
+            let Some(action) = entry.actions.get(menu.selected) else { return };
+            Command::Action {
+                bot: menu.bot,
+                instance: menu.target(entry),
+                action: action.name.clone(),
             }
+        };
+        self.cmd_tx.send(cmd).ok();
+    }
+
+    pub fn close_actions(&mut self) {
+        self.actions = None;
+    }
+
+    /// Start recording in the selected instance's window (the bot's first
+    /// window on a bot row), or stop and write the recording to `<bot>-rec`.
+    /// Only while stopped, so bots' own input does not end up in the
+    /// recording.
+    pub fn toggle_recording(&mut self, platform: &dyn Platform) {
+        if let Some(active) = self.recording.take() {
+            let recording = active.recorder.finish();
+            match recording.save(&active.bots_dir, &active.name) {
+                Ok(path) => logger::info(&format!(
+                    "recorded {} step(s) to {}, restart finger to load it",
+                    recording.steps.len(), path.display(),
+                )),
+                Err(e) => logger::warn(&format!("recording discarded: {}", e)),
+            }
+            return;
+        }
+        if !self.is_stopped() {
+            logger::warn("stop the bots before recording");
+            return;
         }
-        self.cmd_tx.send(Command::StartStop).ok();
+        let target = self.state.lock().unwrap().get(self.selected).map(|e| {
+            let bots_dir = e.script_path.parent().and_then(|d| d.parent())
+                .map(PathBuf::from)
+                .unwrap_or_default();
+            // On an instance row, record in that instance's window
+            let window_id = self.selected_instance
+                .and_then(|n| e.instances.get(n))
+                .map(|i| i.window_id);
+            (e.window_pattern.clone(), window_id, bots_dir, format!("{}-rec", e.name))
+        });
+        let Some((pattern, window_id, bots_dir, name)) = target else { return };
+        match Recorder::start(platform, &pattern, window_id) {
+            Ok(recorder) => {
+                logger::info(&format!(
+                    "recording in \"{}\", press c again to stop", recorder.title()
+                ));
+                self.recording = Some(ActiveRecording { recorder, bots_dir, name });
+            }
+            Err(e) => logger::warn(&format!("cannot record: {}", e)),
+        }
+    }
+
+    pub fn poll_recording(&mut self) {
+        if let Some(active) = self.recording.as_mut() {
+            active.recorder.poll();
+        }
+    }
+
+    pub fn start_stop(&mut self) {
+        if self.recording.is_some() {
+            logger::warn("stop recording before starting the bots");
+            return;
+        }
+        orchestrator::start_stop(&self.orch_state, &self.cmd_tx);
     }
 
     pub fn is_stopped(&self) -> bool {
@@ -120,23 +306,45 @@ impl App {
     }
 
     pub fn restart_all(&mut self) {
+        self.abandon = None;
         self.confirm = Some(ConfirmDialog::new("Restart all bots?"));
     }
 
+    /// Ask before giving up on the instance under the cursor, if it is stuck.
+    pub fn abandon_selected(&mut self) {
+        let Some(n) = self.selected_instance else { return };
+        let key = self.state.lock().unwrap()
+            .get(self.selected)
+            .and_then(|e| e.instances.get(n))
+            .filter(|i| i.stuck.is_some())
+            .map(|i| i.key.clone());
+        if let Some(key) = key {
+            self.abandon = Some((self.selected, key));
+            self.confirm = Some(ConfirmDialog::new("Abandon the stuck instance?"));
+        }
+    }
+
     pub fn clear_logs(&mut self) {
         self.log_messages.clear();
         self.log_scroll = 0;
         finger_core::logger::clear_file();
     }
 
-    pub fn confirm_restart(&mut self) {
+    /// Yes in the confirm dialog: abandon the stuck instance it asked about,
+    /// or restart all bots.
+    pub fn confirm_yes(&mut self) {
         self.confirm = None;
+        if let Some((bot, key)) = self.abandon.take() {
+            self.cmd_tx.send(Command::Abandon { bot, key }).ok();
+            return;
+        }
         self.clear_logs();
         self.cmd_tx.send(Command::Restart(0)).ok();
     }
 
     pub fn cancel_confirm(&mut self) {
         self.confirm = None;
+        self.abandon = None;
     }
 
     pub fn toggle_log(&mut self) {
@@ -144,6 +352,7 @@ impl App {
     }
 
     pub fn quit(&mut self) {
+        finger_core::cancel::request();
         self.cmd_tx.send(Command::Quit).ok();
         self.should_quit = true;
     }
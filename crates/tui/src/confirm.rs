@@ -79,7 +79,7 @@ impl ConfirmDialog {
 }
 
 /// Return a centered `Rect` of `width` columns and `height` rows inside `area`.
-fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+pub(crate) fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
     let x = area.x + area.width.saturating_sub(width) / 2;
     let y = area.y + area.height.saturating_sub(height) / 2;
     Rect::new(x, y, width.min(area.width), height.min(area.height))
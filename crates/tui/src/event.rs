@@ -34,6 +34,7 @@ pub fn run(
 
         // Drain log messages
         app.drain_logs();
+        app.poll_recording();
 
         // Render
         terminal.draw(|f| ui::draw(f, app))?;
@@ -54,14 +55,14 @@ pub fn run(
                             }
                             KeyCode::Char('y') | KeyCode::Char('Y')
                             | KeyCode::Char('r') | KeyCode::Char('R') => {
-                                app.confirm_restart();
+                                app.confirm_yes();
                             }
                             KeyCode::Char('n') | KeyCode::Char('N') => {
                                 app.cancel_confirm();
                             }
                             KeyCode::Enter => {
                                 if app.confirm.as_ref().unwrap().selected {
-                                    app.confirm_restart();
+                                    app.confirm_yes();
                                 } else {
                                     app.cancel_confirm();
                                 }
@@ -74,6 +75,42 @@ pub fn run(
                         continue;
                     }
 
+                    // Options form: text input while editing a field, navigation otherwise
+                    if let Some(editor) = app.options.as_ref() {
+                        if editor.input.is_some() {
+                            match key.code {
+                                KeyCode::Enter => app.edit_options(|ed, e| ed.commit(e)),
+                                KeyCode::Esc => app.edit_options(|ed, _| { ed.cancel_edit(); false }),
+                                KeyCode::Backspace => app.edit_options(|ed, _| { ed.backspace(); false }),
+                                KeyCode::Char(c) => app.edit_options(|ed, _| { ed.push(c); false }),
+                                _ => {}
+                            }
+                        } else {
+                            match key.code {
+                                KeyCode::Up => app.edit_options(|ed, _| { ed.move_up(); false }),
+                                KeyCode::Down => app.edit_options(|ed, e| { ed.move_down(e); false }),
+                                KeyCode::Tab => app.edit_options(|ed, e| { ed.next_scope(e); false }),
+                                KeyCode::Enter | KeyCode::Char(' ') => app.edit_options(|ed, e| ed.begin_edit(e)),
+                                KeyCode::Char('d') | KeyCode::Delete => app.edit_options(|ed, e| ed.clear(e)),
+                                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('o') => app.close_options(),
+                                _ => {}
+                            }
+                        }
+                        continue;
+                    }
+
+                    if app.actions.is_some() {
+                        match key.code {
+                            KeyCode::Up => app.edit_actions(|m, _| m.move_up()),
+                            KeyCode::Down => app.edit_actions(|m, e| m.move_down(e)),
+                            KeyCode::Tab => app.edit_actions(|m, e| m.next_scope(e)),
+                            KeyCode::Enter => app.run_action(),
+                            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('a') => app.close_actions(),
+                            _ => {}
+                        }
+                        continue;
+                    }
+
                     match key.code {
                         KeyCode::Char('q') | KeyCode::Char('Q') => {
                             app.quit();
@@ -88,9 +125,7 @@ pub fn run(
                             app.start_stop();
                         }
                         KeyCode::Char(' ') => {
-                            if app.is_stopped() {
-                                app.toggle_selected();
-                            }
+                            app.toggle_selected();
                         }
                         KeyCode::Char('r') | KeyCode::Char('R') => {
                             app.restart_all();
@@ -101,6 +136,18 @@ pub fn run(
                         KeyCode::Char('m') | KeyCode::Char('M') => {
                             app.toggle_mouse_capture();
                         }
+                        KeyCode::Char('o') | KeyCode::Char('O') => {
+                            app.open_options();
+                        }
+                        KeyCode::Char('a') | KeyCode::Char('A') => {
+                            app.open_actions();
+                        }
+                        KeyCode::Char('c') | KeyCode::Char('C') => {
+                            app.toggle_recording(platform);
+                        }
+                        KeyCode::Char('x') | KeyCode::Char('X') => {
+                            app.abandon_selected();
+                        }
                         _ => {}
                     }
                 }
@@ -1,5 +1,7 @@
+pub mod actions;
 pub mod app;
 pub mod confirm;
+pub mod options;
 pub mod ui;
 pub mod event;
 
@@ -0,0 +1,222 @@
+use ratatui::{
+    Frame,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+use serde_json::Value;
+
+use finger_core::options::{format_value, OptionSource, OptionType};
+use finger_core::types::BotEntry;
+
+use crate::confirm::centered_rect;
+
+/// Modal form for editing one bot's options, bot-wide or per instance.
+pub struct OptionsEditor {
+    pub bot: usize,
+    /// 0 = bot-wide, n = the bot's n-th instance
+    pub scope: usize,
+    pub selected: usize,
+    /// Text buffer while a field is being edited
+    pub input: Option<String>,
+    pub error: Option<String>,
+}
+
+impl OptionsEditor {
+    pub fn new(bot: usize) -> Self {
+        Self { bot, scope: 0, selected: 0, input: None, error: None }
+    }
+
+    /// Instance key of the current scope, None when editing bot-wide values.
+    fn scope_key<'a>(&self, entry: &'a BotEntry) -> Option<&'a str> {
+        match self.scope {
+            0 => None,
+            n => entry.instances.get(n - 1).map(|i| i.key.as_str()),
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+        self.error = None;
+    }
+
+    pub fn move_down(&mut self, entry: &BotEntry) {
+        if self.selected + 1 < entry.options.len() {
+            self.selected += 1;
+        }
+        self.error = None;
+    }
+
+    pub fn next_scope(&mut self, entry: &BotEntry) {
+        self.scope = (self.scope + 1) % (entry.instances.len() + 1);
+        self.error = None;
+    }
+
+    /// Start editing the selected field; booleans are flipped in place.
+    /// Returns true if a value changed.
+    pub fn begin_edit(&mut self, entry: &mut BotEntry) -> bool {
+        let Some(spec) = entry.options.get(self.selected) else { return false };
+        let (current, _) = entry.option_values.effective(spec, self.scope_key(entry));
+        if spec.kind == OptionType::Boolean {
+            let flipped = !current.as_bool().unwrap_or(false);
+            self.set(entry, Some(Value::Bool(flipped)));
+            return true;
+        }
+        self.input = Some(match current {
+            Value::Null => String::new(),
+            v => format_value(v),
+        });
+        self.error = None;
+        false
+    }
+
+    pub fn push(&mut self, c: char) {
+        if let Some(buf) = self.input.as_mut() {
+            buf.push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(buf) = self.input.as_mut() {
+            buf.pop();
+        }
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.input = None;
+        self.error = None;
+    }
+
+    /// Parse the input buffer and store it in the current scope.
+    /// Returns true if a value changed; parse errors stay in the dialog.
+    pub fn commit(&mut self, entry: &mut BotEntry) -> bool {
+        let Some(text) = self.input.as_deref() else { return false };
+        let Some(spec) = entry.options.get(self.selected) else { return false };
+        match spec.parse(text) {
+            Ok(value) => {
+                self.set(entry, Some(value));
+                self.input = None;
+                self.error = None;
+                true
+            }
+            Err(e) => {
+                self.error = Some(e);
+                false
+            }
+        }
+    }
+
+    /// Drop the override for the selected field in the current scope.
+    pub fn clear(&mut self, entry: &mut BotEntry) -> bool {
+        self.set(entry, None);
+        true
+    }
+
+    fn set(&self, entry: &mut BotEntry, value: Option<Value>) {
+        let Some(spec) = entry.options.get(self.selected) else { return };
+        let name = spec.name.clone();
+        let map = match self.scope_key(entry).map(str::to_string) {
+            None => &mut entry.option_values.bot,
+            Some(key) => entry.option_values.instances.entry(key).or_default(),
+        };
+        match value {
+            Some(v) => { map.insert(name, v); }
+            None => { map.remove(&name); }
+        }
+        entry.option_values.instances.retain(|_, m| !m.is_empty());
+    }
+
+    pub fn render(&self, f: &mut Frame, entry: &BotEntry) {
+        let height = (entry.options.len() as u16) * 2 + 6;
+        let area = centered_rect(64, height, f.area());
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(format!(" Options: {} ", entry.name));
+
+        let mut lines: Vec<Line> = Vec::new();
+
+        // Scope selector: bot-wide, then one tab per instance
+        let mut scopes = vec![Span::styled(" scope ", Style::default().fg(Color::DarkGray))];
+        let labels = std::iter::once("all".to_string())
+            .chain(entry.instances.iter().map(|i| format!("#{}", i.slot)));
+        for (i, label) in labels.enumerate() {
+            let style = if i == self.scope {
+                Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Cyan)
+            };
+            scopes.push(Span::styled(format!(" {} ", label), style));
+            scopes.push(Span::raw(" "));
+        }
+        lines.push(Line::from(scopes));
+        lines.push(Line::from(""));
+
+        let scope_key = self.scope_key(entry);
+        for (i, spec) in entry.options.iter().enumerate() {
+            let is_selected = i == self.selected;
+            let (value, source) = entry.option_values.effective(spec, scope_key);
+
+            let value_span = match (&self.input, is_selected) {
+                (Some(buf), true) => Span::styled(
+                    format!("{}▏", buf),
+                    Style::default().fg(Color::Black).bg(Color::White),
+                ),
+                _ => Span::styled(format_value(value), Style::default().fg(Color::Cyan)),
+            };
+            let source_label = match source {
+                OptionSource::Default => "",
+                OptionSource::Bot => "  (bot)",
+                OptionSource::Instance => "  (instance)",
+            };
+            lines.push(Line::from(vec![
+                Span::raw(if is_selected { " > " } else { "   " }),
+                Span::styled(spec.name.clone(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+                Span::raw(" = "),
+                value_span,
+                Span::styled(source_label, Style::default().fg(Color::Yellow)),
+            ]));
+
+            let range = match (spec.min, spec.max) {
+                (None, None) => String::new(),
+                (min, max) => format!(
+                    " {}..{}",
+                    min.map_or(String::new(), |v| v.to_string()),
+                    max.map_or(String::new(), |v| v.to_string()),
+                ),
+            };
+            lines.push(Line::from(Span::styled(
+                format!("     [{}{}] {}", spec.kind.name(), range, spec.description),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+
+        lines.push(Line::from(""));
+        lines.push(match &self.error {
+            Some(e) => Line::from(Span::styled(format!(" {}", e), Style::default().fg(Color::Red))),
+            None if self.input.is_some() => Line::from(vec![
+                Span::styled(" enter", Style::default().fg(Color::Yellow)),
+                Span::raw(" save  "),
+                Span::styled("esc", Style::default().fg(Color::Yellow)),
+                Span::raw(" cancel"),
+            ]),
+            None => Line::from(vec![
+                Span::styled(" ↑↓", Style::default().fg(Color::Yellow)),
+                Span::raw(" move  "),
+                Span::styled("tab", Style::default().fg(Color::Yellow)),
+                Span::raw(" scope  "),
+                Span::styled("enter", Style::default().fg(Color::Yellow)),
+                Span::raw(" edit  "),
+                Span::styled("d", Style::default().fg(Color::Yellow)),
+                Span::raw(" reset  "),
+                Span::styled("esc", Style::default().fg(Color::Yellow)),
+                Span::raw(" close"),
+            ]),
+        });
+
+        let para = Paragraph::new(lines).block(block).wrap(Wrap { trim: false });
+        f.render_widget(para, area);
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout},
@@ -6,9 +8,12 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Wrap},
 };
 
-use finger_core::types::OrchestratorState;
+use finger_core::types::{OrchestratorState, Status, StatusLevel};
 use crate::App;
 
+/// Scheduling lateness below this is normal jitter and not shown
+const LATE_SHOWN: Duration = Duration::from_millis(100);
+
 pub fn draw(f: &mut Frame, app: &App) {
     let chunks = if app.log_visible {
         Layout::default()
@@ -24,9 +29,15 @@ pub fn draw(f: &mut Frame, app: &App) {
     // -- Left panel: bot list --
 
     // Orchestrator state banner (rendered separately as full-width bar)
+    let recording_label;
     let (banner_label, banner_bg, is_stopped) = {
         let orch = app.orch_state.lock().unwrap();
         match *orch {
+            _ if app.recording.is_some() => {
+                let steps = app.recording.as_ref().map_or(0, |r| r.recorder.len());
+                recording_label = format!("RECORDING {} step(s) (Press C to stop)", steps);
+                (recording_label.as_str(), Color::Magenta, true)
+            }
             OrchestratorState::Running  => ("RUNNING (Press K to stop)",  Color::Green,  false),
             OrchestratorState::Stopping => ("STOPPING...",                 Color::Yellow, false),
             OrchestratorState::Stopped  => ("STOPPED (Press K to start)",  Color::Red,    true),
@@ -47,7 +58,13 @@ pub fn draw(f: &mut Frame, app: &App) {
         Span::styled("space", Style::default().fg(Color::Yellow)),
         Span::raw(" select  "),
         Span::styled("r", Style::default().fg(Color::Yellow)),
-        Span::raw(" restart"),
+        Span::raw(" restart  "),
+        Span::styled("o", Style::default().fg(Color::Yellow)),
+        Span::raw(" options  "),
+        Span::styled("a", Style::default().fg(Color::Yellow)),
+        Span::raw(" actions  "),
+        Span::styled("c", Style::default().fg(Color::Yellow)),
+        Span::raw(" record"),
     ]));
     lines.push(Line::from(""));
 
@@ -56,7 +73,10 @@ pub fn draw(f: &mut Frame, app: &App) {
 
         for (i, entry) in entries.iter().enumerate() {
             let is_selected = i == app.selected;
-            let prefix = if is_selected { "> " } else { "  " };
+            // The cursor sits on an instance row instead when one is selected
+            let cursor_inst = app.selected_instance
+                .filter(|n| is_selected && entry.enabled && *n < entry.instances.len());
+            let prefix = if is_selected && cursor_inst.is_none() { "> " } else { "  " };
 
             let (checkbox, check_color) = if is_stopped && entry.enabled {
                 (" ● ", banner_bg)
@@ -83,34 +103,62 @@ pub fn draw(f: &mut Frame, app: &App) {
             }
             lines.push(Line::from(spans));
 
+            // Why the schedule keeps the bot off, and until when
+            if let (true, Some(hold)) = (entry.enabled, &entry.hold) {
+                lines.push(Line::from(Span::styled(
+                    format!("    ◷ {}", hold.describe()),
+                    Style::default().fg(Color::Blue),
+                )));
+            }
+
             // Instance lines (only for enabled bots)
             if entry.enabled {
-                for inst in &entry.instances {
-                    let status_color = if inst.error.is_some() {
-                        Color::Red
-                    } else {
-                        Color::Cyan
-                    };
-
-                    let status_text = if let Some(ref e) = inst.error {
-                        format!(" err: {}", e)
-                    } else if !inst.status.is_empty() {
-                        format!(" {}", inst.status)
-                    } else {
-                        String::new()
-                    };
-
-                    lines.push(Line::from(vec![
+                for (n, inst) in entry.instances.iter().enumerate() {
+                    let cursor = if cursor_inst == Some(n) { "  > " } else { "    " };
+                    let title_color = if inst.enabled { Color::Yellow } else { Color::DarkGray };
+                    let mut spans = vec![
+                        Span::raw(cursor),
                         Span::styled(
-                            format!("    {} ", inst.window_title),
-                            Style::default().fg(Color::Yellow),
+                            format!("{} ", inst.window_title),
+                            Style::default().fg(title_color),
                         ),
                         Span::styled(
                             format!("#{}", inst.window_id),
                             Style::default().fg(Color::DarkGray),
                         ),
-                        Span::styled(status_text, Style::default().fg(status_color)),
-                    ]));
+                    ];
+
+                    if !inst.enabled {
+                        spans.push(Span::styled(" off", Style::default().fg(Color::DarkGray)));
+                        lines.push(Line::from(spans));
+                    } else if let Some((since, step)) = inst.stuck {
+                        spans.push(Span::styled(
+                            format!(" stuck {}s in {}", since.elapsed().as_secs(), step),
+                            Style::default().fg(Color::Red),
+                        ));
+                        spans.push(Span::styled(" (x to abandon)", Style::default().fg(Color::DarkGray)));
+                        lines.push(Line::from(spans));
+                    } else if let Some(ref e) = inst.error {
+                        let text = if inst.retries > 0 {
+                            format!(" err (restart {}): {}", inst.retries, e)
+                        } else {
+                            format!(" err: {}", e)
+                        };
+                        spans.push(Span::styled(text, Style::default().fg(Color::Red)));
+                        lines.push(Line::from(spans));
+                    } else {
+                        spans.extend(status_spans(&inst.status));
+                        if inst.lateness >= LATE_SHOWN {
+                            spans.push(Span::styled(
+                                format!(" late {}ms", inst.lateness.as_millis()),
+                                Style::default().fg(Color::DarkGray),
+                            ));
+                        }
+                        lines.push(Line::from(spans));
+                        if !inst.status.fields.is_empty() {
+                            lines.push(field_line(&inst.status));
+                        }
+                    }
                 }
             }
         }
@@ -165,12 +213,70 @@ pub fn draw(f: &mut Frame, app: &App) {
         f.render_widget(log_panel, chunks[1]);
     }
 
+    // -- Options form overlay --
+    if let Some(ref editor) = app.options {
+        let entries = app.state.lock().unwrap();
+        if let Some(entry) = entries.get(editor.bot) {
+            editor.render(f, entry);
+        }
+    }
+
+    // -- Action menu overlay --
+    if let Some(ref menu) = app.actions {
+        let entries = app.state.lock().unwrap();
+        if let Some(entry) = entries.get(menu.bot) {
+            menu.render(f, entry);
+        }
+    }
+
     // -- Confirm dialog overlay --
     if let Some(ref dialog) = app.confirm {
         dialog.render(f);
     }
 }
 
+/// Width of the inline progress gauge, in cells
+const GAUGE_WIDTH: usize = 10;
+
+/// Badge, text and progress gauge of an instance status.
+fn status_spans(status: &Status) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    if let Some(level) = status.level {
+        let (label, color) = match level {
+            StatusLevel::Ok => ("OK", Color::Green),
+            StatusLevel::Warn => ("WARN", Color::Yellow),
+            StatusLevel::Error => ("ERR", Color::Red),
+        };
+        spans.push(Span::raw(" "));
+        spans.push(Span::styled(
+            format!(" {} ", label),
+            Style::default().fg(Color::Black).bg(color).add_modifier(Modifier::BOLD),
+        ));
+    }
+    if !status.text.is_empty() {
+        spans.push(Span::styled(format!(" {}", status.text), Style::default().fg(Color::Cyan)));
+    }
+    if let Some(p) = status.progress {
+        let filled = (p * GAUGE_WIDTH as f64).round() as usize;
+        spans.push(Span::raw(" "));
+        spans.push(Span::styled("█".repeat(filled), Style::default().fg(Color::Green)));
+        spans.push(Span::styled("░".repeat(GAUGE_WIDTH - filled), Style::default().fg(Color::DarkGray)));
+        spans.push(Span::styled(format!(" {:>3.0}%", p * 100.0), Style::default().fg(Color::Gray)));
+    }
+    spans
+}
+
+/// Key/value fields of an instance status, one padded column per field.
+fn field_line(status: &Status) -> Line<'static> {
+    let mut spans = vec![Span::raw("      ")];
+    for (key, value) in &status.fields {
+        spans.push(Span::styled(format!("{} ", key), Style::default().fg(Color::DarkGray)));
+        spans.push(Span::styled(format!("{:<8}", value), Style::default().fg(Color::White)));
+        spans.push(Span::raw("  "));
+    }
+    Line::from(spans)
+}
+
 /// Parse a structured log line (level\x1fprefix\x1fcolor\x1ftimestamp\x1fmessage)
 /// into a colored Line for TUI rendering.
 fn parse_log_line(raw: &str) -> Line<'_> {
@@ -1,52 +1,194 @@
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, mpsc};
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail};
+use clap::{Parser, Subcommand};
 use crossterm::{
     execute,
     event::{EnableMouseCapture, DisableMouseCapture},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{Terminal, backend::CrosstermBackend};
+use serde_json::{Value, json};
 
-use finger_core::{logger, orchestrator, settings::Settings};
-use finger_core::platform::create_platform;
-use finger_core::types::{Command, OrchestratorState};
+use finger_core::{control, humanize, logger, lua_rt, orchestrator, recorder::Recorder, schedule::Schedule, settings::Settings, store};
+use finger_core::lua_rt::LuaBot;
+use finger_core::platform::{Platform, create_platform};
+use finger_core::types::{BotEntry, Command, OrchestratorState};
+
+/// Runs Lua bots against game windows. Without a command, opens the TUI.
+#[derive(Parser)]
+#[command(name = "finger", version)]
+struct Cli {
+    /// Use the stub platform: no real windows or input
+    #[arg(long, global = true)]
+    stub: bool,
+    /// Bots directory [default: ./bots]
+    #[arg(long, global = true, value_name = "DIR")]
+    bots: Option<PathBuf>,
+    /// Shared Lua modules [default: <bots>/lib]
+    #[arg(long, global = true, value_name = "DIR")]
+    lib: Option<PathBuf>,
+    /// Log directory [default: ./logs]
+    #[arg(long, global = true, value_name = "DIR")]
+    logs: Option<PathBuf>,
+    /// F.store directory [default: ./data]
+    #[arg(long, global = true, value_name = "DIR")]
+    data: Option<PathBuf>,
+    /// Settings file [default: ./settings.json]
+    #[arg(long, global = true, value_name = "FILE")]
+    settings: Option<PathBuf>,
+    /// Control endpoint for --headless, run and ctl [default: ./finger.sock]
+    #[arg(long, global = true, value_name = "PATH")]
+    control: Option<PathBuf>,
+    /// Run the enabled bots without the TUI, controlled through `finger ctl`
+    #[arg(long)]
+    headless: bool,
+    #[command(subcommand)]
+    command: Option<Cmd>,
+}
+
+#[derive(Subcommand)]
+enum Cmd {
+    /// List bots, their window patterns and matching windows
+    List,
+    /// Run the given bots without the TUI, controlled through `finger ctl`
+    Run {
+        #[arg(required = true, value_name = "BOT")]
+        names: Vec<String>,
+    },
+    /// Run one tick of a bot and print its status
+    Once {
+        bot: String,
+        /// Which of the bot's windows, 1-based
+        #[arg(long, default_value_t = 1)]
+        instance: usize,
+    },
+    /// Load every bot's metadata and report errors
+    Check,
+    /// Record clicks and keys in a window and write them out as a bot
+    Record {
+        /// Window title pattern
+        pattern: String,
+        /// Name of the new bot
+        #[arg(default_value = "recorded")]
+        name: String,
+    },
+    /// Control a finger running headless
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCmd,
+    },
+}
+
+#[derive(Subcommand)]
+enum CtlCmd {
+    /// Bots, instances and their status
+    List,
+    /// Start the enabled bots
+    Start,
+    /// Stop all bots
+    Stop,
+    /// Restart all bots
+    Restart,
+    /// Stop the bots and exit
+    Quit,
+    /// Switch a bot, or one instance by key or id, on
+    Enable { bot: String, instance: Option<String> },
+    /// Switch a bot, or one instance by key or id, off
+    Disable { bot: String, instance: Option<String> },
+    /// Run a bot action, on every instance if none is given
+    Action { bot: String, action: String, instance: Option<String> },
+    /// Give up on a stuck instance, by key or id
+    Abandon { bot: String, instance: String },
+    /// Follow the log
+    Logs,
+    /// Print the list whenever it changes
+    Watch,
+    /// Make a raw JSON-RPC call
+    Call { method: String, params: Option<String> },
+}
+
+/// Where finger reads and writes its files
+struct Paths {
+    bots: PathBuf,
+    lib: PathBuf,
+    logs: PathBuf,
+    data: PathBuf,
+    settings: PathBuf,
+    control: PathBuf,
+}
+
+impl Cli {
+    /// Paths from the flags, defaulting to the working directory.
+    fn paths(&self) -> Paths {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let bots = self.bots.clone().unwrap_or_else(|| cwd.join("bots"));
+        Paths {
+            lib: self.lib.clone().unwrap_or_else(|| bots.join("lib")),
+            bots,
+            logs: self.logs.clone().unwrap_or_else(|| cwd.join("logs")),
+            data: self.data.clone().unwrap_or_else(|| cwd.join("data")),
+            settings: self.settings.clone().unwrap_or_else(|| cwd.join("settings.json")),
+            control: self.control.clone().unwrap_or_else(|| control::endpoint(&cwd)),
+        }
+    }
+}
 
 fn main() -> Result<()> {
-    let force_stub = std::env::args().any(|a| a == "--stub");
+    let cli = Cli::parse();
+    let paths = cli.paths();
+    let force_stub = cli.stub;
 
-    // Resolve bots directory (next to the binary, or cwd/bots)
-    let bots_dir = {
-        let mut d = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-        d.push("bots");
-        d
-    };
-    let logs_dir = {
-        let mut d = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-        d.push("logs");
-        d
+    // Commands that only look log to stderr, so they do not truncate the
+    // log file of a finger that may be running here
+    let command = match cli.command {
+        Some(Cmd::Ctl { command }) => return ctl(&paths.control, command),
+        Some(Cmd::Check) => {
+            logger::init_stderr();
+            return check(&paths);
+        }
+        Some(Cmd::List) => {
+            logger::init_stderr();
+            return list(&paths, force_stub);
+        }
+        command => command,
     };
 
-    // Init logger
-    logger::init(&logs_dir);
+    logger::init(&paths.logs);
+    store::init(&paths.data);
+    lua_rt::set_lib_dir(&paths.lib);
 
     // Create platform
     let platform = create_platform(force_stub);
 
+    let run_only = match command {
+        Some(Cmd::Record { pattern, name }) => return record(platform.as_ref(), &pattern, &paths.bots, &name),
+        Some(Cmd::Once { bot, instance }) => return once(&paths, platform, &bot, instance),
+        Some(Cmd::Run { names }) => Some(names),
+        _ => None,
+    };
+
     // Load bots and scan instances
-    let mut entries = orchestrator::load_bots(&bots_dir);
+    let mut entries = orchestrator::load_bots(&paths.bots);
     orchestrator::scan_instances(&mut entries, platform.as_ref());
 
     // Restore enabled state from settings
-    let settings_path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")).join("settings.json");
-    let settings = Settings::load(&settings_path);
-    for entry in &mut entries {
-        if settings.enabled_bots.contains(&entry.name) {
-            entry.enabled = true;
+    let settings = Settings::load(&paths.settings);
+    humanize::init(settings.humanize.clone().unwrap_or_default());
+    apply_settings(&mut entries, &settings);
+
+    // `finger run a b`: exactly these bots, leaving the saved choice alone
+    if let Some(names) = &run_only {
+        if let Some(unknown) = names.iter().find(|n| !entries.iter().any(|e| &e.name == *n)) {
+            bail!("no bot named \"{}\" in {}", unknown, paths.bots.display());
+        }
+        for entry in &mut entries {
+            entry.enabled = names.contains(&entry.name);
         }
     }
 
@@ -64,6 +206,13 @@ fn main() -> Result<()> {
     logger::set_tui_sender(log_tx);
     logger::info("finger started");
 
+    if cli.headless || run_only.is_some() {
+        // Switching bots on and off under `run` is not saved
+        let settings_path = run_only.is_none().then(|| paths.settings.clone());
+        let headless = Headless { state, orch_state, log_rx, cmd_tx, cmd_rx, settings_path };
+        return run_headless(&paths, headless, platform, force_stub);
+    }
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -77,14 +226,14 @@ fn main() -> Result<()> {
         Arc::clone(&orch_state),
         log_rx,
         cmd_tx,
-        settings_path,
+        paths.settings.clone(),
     );
 
     // Spawn orchestrator on a background thread
     let orch_bot_state = Arc::clone(&state);
     let orch_run_state = Arc::clone(&orch_state);
     let orch_platform = create_platform(force_stub);
-    let orch_bots_dir = bots_dir.clone();
+    let orch_bots_dir = paths.bots.clone();
     thread::spawn(move || {
         orchestrator::orchestrate(orch_bot_state, orch_run_state, orch_platform, orch_bots_dir, cmd_rx);
     });
@@ -101,5 +250,274 @@ fn main() -> Result<()> {
     execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
     terminal.show_cursor()?;
 
+    // Persist F.store even if the orchestrator thread is still busy
+    store::flush_all();
+
     result
 }
+
+/// Enabled bots and instances, option values and schedules from settings.
+fn apply_settings(entries: &mut [BotEntry], settings: &Settings) {
+    for entry in entries {
+        if settings.enabled_bots.contains(&entry.name) {
+            entry.enabled = true;
+        }
+        for inst in &mut entry.instances {
+            inst.enabled = !settings.disabled_instances.contains(&inst.key);
+        }
+        if let Some(values) = settings.options.get(&entry.name) {
+            entry.option_values = values.clone();
+        }
+        if let Some(config) = settings.schedules.get(&entry.name) {
+            match Schedule::parse(config) {
+                Ok(schedule) => entry.schedule = Some(schedule),
+                Err(e) => logger::error(&format!("schedule for {} ignored: {}", entry.name, e)),
+            }
+        }
+    }
+}
+
+/// `finger list`: every bot with its pattern and the windows it would run in.
+fn list(paths: &Paths, force_stub: bool) -> Result<()> {
+    lua_rt::set_lib_dir(&paths.lib);
+    let platform = create_platform(force_stub);
+    let mut entries = orchestrator::load_bots(&paths.bots);
+    orchestrator::scan_instances(&mut entries, platform.as_ref());
+    apply_settings(&mut entries, &Settings::load(&paths.settings));
+
+    for entry in &entries {
+        let on = if entry.enabled { "on " } else { "off" };
+        println!("{} {}  /{}/  {}", on, entry.name, entry.window_pattern, entry.description);
+        if entry.instances.is_empty() {
+            println!("      (no matching window)");
+        }
+        for inst in &entry.instances {
+            let off = if inst.enabled { "" } else { " (off)" };
+            println!("      #{} {:>8}  {}{}", inst.slot, inst.window_id, inst.window_title, off);
+        }
+    }
+    let broken = orchestrator::find_bot_dirs(&paths.bots).len() - entries.len();
+    if broken > 0 {
+        println!("{} bot(s) failed to load, see `finger check`", broken);
+    }
+    Ok(())
+}
+
+/// `finger check`: load every bot's metadata and the schedules in settings,
+/// reporting what is broken. Fails if anything is.
+fn check(paths: &Paths) -> Result<()> {
+    lua_rt::set_lib_dir(&paths.lib);
+    let mut errors = 0;
+    let scripts = orchestrator::find_bot_dirs(&paths.bots);
+    if scripts.is_empty() {
+        errors += 1;
+        println!("error  no bots in {}", paths.bots.display());
+    }
+    for path in scripts {
+        let name = orchestrator::derive_bot_name(&path, &paths.bots);
+        match LuaBot::load_meta(&path) {
+            Ok(_) => println!("ok     {}", name),
+            Err(e) => {
+                errors += 1;
+                println!("error  {}: {}", name, e);
+            }
+        }
+    }
+    let settings = Settings::read(&paths.settings).unwrap_or_else(|e| {
+        errors += 1;
+        println!("error  {}: {}", paths.settings.display(), e);
+        Settings::default()
+    });
+    for (name, config) in &settings.schedules {
+        if let Err(e) = Schedule::parse(config) {
+            errors += 1;
+            println!("error  schedule for {}: {}", name, e);
+        }
+    }
+    if errors > 0 {
+        bail!("{} error(s)", errors);
+    }
+    Ok(())
+}
+
+/// `finger once <bot>`: start one instance, run a single tick, print the
+/// log and its status, and stop it again.
+fn once(paths: &Paths, platform: Box<dyn Platform>, bot: &str, slot: usize) -> Result<()> {
+    let mut entries = orchestrator::load_bots(&paths.bots);
+    let idx = entries.iter().position(|e| e.name == bot)
+        .ok_or_else(|| anyhow!("no bot named \"{}\" in {} (see `finger check`)", bot, paths.bots.display()))?;
+    let mut entries = vec![entries.swap_remove(idx)];
+    orchestrator::scan_instances(&mut entries, platform.as_ref());
+    let settings = Settings::load(&paths.settings);
+    humanize::init(settings.humanize.clone().unwrap_or_default());
+    apply_settings(&mut entries, &settings);
+    let entry = entries.remove(0);
+    let n = entry.instances.iter().position(|i| i.slot == slot).ok_or_else(|| anyhow!(
+        "{} has {} window(s) matching /{}/, no instance {}",
+        entry.name, entry.instances.len(), entry.window_pattern, slot,
+    ))?;
+
+    // Tick on a worker so the log can be printed as it happens
+    let (log_tx, log_rx) = mpsc::channel::<String>();
+    logger::set_tui_sender(log_tx);
+    let worker = thread::spawn(move || orchestrator::run_once(&entry, &entry.instances[n], platform.as_ref()));
+    while !worker.is_finished() {
+        for line in log_rx.try_iter() {
+            println!("{}", logger::plain_line(&line));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    for line in log_rx.try_iter() {
+        println!("{}", logger::plain_line(&line));
+    }
+    store::flush_all();
+
+    let (cooldown, status) = worker.join()
+        .map_err(|_| anyhow!("tick panicked"))?
+        .map_err(|e| anyhow!(e))?;
+    if !status.text.is_empty() {
+        println!("status: {}", status.text);
+    }
+    if let Some(p) = status.progress {
+        println!("progress: {:.0}%", p * 100.0);
+    }
+    for (k, v) in &status.fields {
+        println!("{}: {}", k, v);
+    }
+    println!("next tick in {}s", cooldown);
+    Ok(())
+}
+
+/// State a headless finger hands to the orchestrator and control socket
+struct Headless {
+    state: Arc<Mutex<Vec<BotEntry>>>,
+    orch_state: Arc<Mutex<OrchestratorState>>,
+    log_rx: mpsc::Receiver<String>,
+    cmd_tx: mpsc::Sender<Command>,
+    cmd_rx: mpsc::Receiver<Command>,
+    settings_path: Option<PathBuf>,
+}
+
+/// Run the bots without the TUI, controlled through the control socket
+/// (`finger ctl`) and the global hotkey. Logs go to stdout.
+fn run_headless(paths: &Paths, h: Headless, platform: Box<dyn Platform>, force_stub: bool) -> Result<()> {
+    let endpoint = &paths.control;
+    control::serve(endpoint, Arc::clone(&h.state), Arc::clone(&h.orch_state), h.cmd_tx.clone(), h.settings_path, h.log_rx)
+        .map_err(|e| anyhow!("cannot listen on {}: {}", endpoint.display(), e))?;
+    logger::info(&format!("running headless, control endpoint {}", endpoint.display()));
+
+    let (state, orch_state, cmd_rx) = (h.state, Arc::clone(&h.orch_state), h.cmd_rx);
+    let orch_platform = create_platform(force_stub);
+    let bots_dir = paths.bots.clone();
+    let orchestrator = thread::spawn(move || {
+        orchestrator::orchestrate(state, orch_state, orch_platform, bots_dir, cmd_rx);
+    });
+
+    let hotkey_flag = Arc::new(AtomicBool::new(false));
+    platform.start_hotkey_listener(Arc::clone(&hotkey_flag));
+
+    orchestrator::start_stop(&h.orch_state, &h.cmd_tx);
+    // Until `finger ctl quit`; the hotkey only stops the bots
+    while !orchestrator.is_finished() {
+        if hotkey_flag.swap(false, Ordering::Acquire)
+            && *h.orch_state.lock().unwrap() == OrchestratorState::Running
+        {
+            orchestrator::start_stop(&h.orch_state, &h.cmd_tx);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    store::flush_all();
+    control::cleanup(endpoint);
+    Ok(())
+}
+
+/// `finger ctl <command>`: one call to a running finger, printing its result.
+fn ctl(endpoint: &Path, command: CtlCmd) -> Result<()> {
+    let mut client = control::Client::connect(endpoint)?;
+    let (method, params) = match command {
+        CtlCmd::List => ("list", Value::Null),
+        CtlCmd::Start => ("start", Value::Null),
+        CtlCmd::Stop => ("stop", Value::Null),
+        CtlCmd::Restart => ("restart", Value::Null),
+        CtlCmd::Quit => ("quit", Value::Null),
+        CtlCmd::Enable { bot, instance } => ("enable", json!({ "bot": bot, "instance": instance })),
+        CtlCmd::Disable { bot, instance } => ("disable", json!({ "bot": bot, "instance": instance })),
+        CtlCmd::Action { bot, action, instance } => ("action", json!({
+            "bot": bot, "action": action, "instance": instance,
+        })),
+        CtlCmd::Abandon { bot, instance } => ("abandon", json!({ "bot": bot, "instance": instance })),
+        CtlCmd::Logs | CtlCmd::Watch => {
+            let logs = matches!(command, CtlCmd::Logs);
+            client.call("subscribe", json!({ "logs": logs, "status": !logs }))?;
+            loop {
+                match client.notification()? {
+                    (kind, params) if kind == "log" => {
+                        println!("{}", params["line"].as_str().unwrap_or_default());
+                    }
+                    (_, params) => print_list(&params),
+                }
+            }
+        }
+        CtlCmd::Call { method, params } => {
+            let params = match params {
+                Some(p) => serde_json::from_str(&p)?,
+                None => Value::Null,
+            };
+            let result = client.call(&method, params)?;
+            println!("{}", serde_json::to_string_pretty(&result)?);
+            return Ok(());
+        }
+    };
+    let result = client.call(method, params)?;
+    if method == "list" {
+        print_list(&result);
+    } else if !result.is_null() {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    }
+    Ok(())
+}
+
+/// Print a `list` result as one line per bot and instance.
+fn print_list(list: &Value) {
+    println!("finger is {}", list["state"].as_str().unwrap_or("?"));
+    for bot in list["bots"].as_array().into_iter().flatten() {
+        let on = if bot["enabled"].as_bool() == Some(true) { "on " } else { "off" };
+        println!("{} {}  {}", on, bot["name"].as_str().unwrap_or_default(), bot["description"].as_str().unwrap_or_default());
+        if let Some(hold) = bot["hold"].as_str() {
+            println!("      ◷ {}", hold);
+        }
+        for inst in bot["instances"].as_array().into_iter().flatten() {
+            let on = if inst["enabled"].as_bool() == Some(true) { "" } else { " (off)" };
+            println!("      {} {}{}  {}", inst["key"].as_str().unwrap_or_default(), inst["title"].as_str().unwrap_or_default(),
+                on, inst["status"].as_str().unwrap_or_default());
+            if let Some(stuck) = inst["stuck"].as_object() {
+                println!("        ⧗ stuck {}s in {}", stuck["secs"], stuck["step"].as_str().unwrap_or_default());
+            }
+            if let Some(err) = inst["error"].as_str() {
+                println!("        ✗ {}", err);
+            }
+        }
+    }
+}
+
+/// Record clicks and keys in the first window matching `pattern` until Enter
+/// is pressed, then write the bot skeleton to `bots_dir/name`.
+fn record(platform: &dyn Platform, pattern: &str, bots_dir: &Path, name: &str) -> Result<()> {
+    let mut recorder = Recorder::start(platform, pattern, None)?;
+    println!("Recording in \"{}\" — click into the window, then press Enter here to stop.", recorder.title());
+
+    let (done_tx, done_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = io::stdin().read_line(&mut String::new());
+        let _ = done_tx.send(());
+    });
+    while done_rx.recv_timeout(Duration::from_millis(50)).is_err() {
+        recorder.poll();
+    }
+
+    let recording = recorder.finish();
+    let path = recording.save(bots_dir, name)?;
+    println!("Recorded {} step(s) to {}", recording.steps.len(), path.display());
+    Ok(())
+}